@@ -0,0 +1,55 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mini_graph::buffer::Buffer;
+use mini_graph::simd;
+
+const FRAME_SIZE: usize = 512;
+
+fn scalar_mul_add(dst: &mut [f32], src: &[f32], scalar: f32) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d += *s * scalar;
+    }
+}
+
+fn scalar_clamp(dst: &mut [f32], min: f32, max: f32) {
+    for d in dst.iter_mut() {
+        *d = d.clamp(min, max);
+    }
+}
+
+fn ramp() -> Buffer<FRAME_SIZE> {
+    let mut buffer = Buffer::<FRAME_SIZE>::default();
+    for (i, sample) in buffer.iter_mut().enumerate() {
+        *sample = (i as f32 / FRAME_SIZE as f32) * 4.0 - 2.0;
+    }
+    buffer
+}
+
+fn bench_mul_add(c: &mut Criterion) {
+    let src = ramp();
+    let mut dst = Buffer::<FRAME_SIZE>::default();
+    let mut group = c.benchmark_group("mul_add");
+
+    group.bench_function("scalar", |b| {
+        b.iter(|| scalar_mul_add(black_box(&mut dst), black_box(&src), 0.5));
+    });
+    group.bench_function("simd", |b| {
+        b.iter(|| simd::mul_add(black_box(&mut dst), black_box(&src), 0.5));
+    });
+    group.finish();
+}
+
+fn bench_clamp(c: &mut Criterion) {
+    let mut dst = ramp();
+    let mut group = c.benchmark_group("clamp");
+
+    group.bench_function("scalar", |b| {
+        b.iter(|| scalar_clamp(black_box(&mut dst), -1.0, 1.0));
+    });
+    group.bench_function("simd", |b| {
+        b.iter(|| simd::clamp(black_box(&mut dst), -1.0, 1.0));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_mul_add, bench_clamp);
+criterion_main!(benches);
@@ -1,14 +1,62 @@
+use crate::transport::Transport;
+
+/// The sample rate used by graphs until told otherwise.
+pub const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+
+/// Information shared with every node while a block is processed.
 pub struct AudioContext {
     sample_rate: f32, // Stored as f32 as it's frequently required for division
+    frames: usize,
+    transport: Transport,
 }
 impl AudioContext {
     pub fn new(sample_rate: u32) -> Self {
         Self {
-            sample_rate: sample_rate as f32
+            sample_rate: sample_rate as f32,
+            frames: 0,
+            transport: Transport::new(sample_rate as f32),
         }
     }
     #[inline(always)]
     pub fn get_sample_rate(&self) -> &f32 {
         &self.sample_rate
     }
-}
\ No newline at end of file
+    /// The number of frames to compute this block. Nodes only read and
+    /// write the first `frames` samples of each buffer, which is at most
+    /// the graph's `BUFFER_SIZE`, and may change from one block to the
+    /// next.
+    #[inline(always)]
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+    /// The graph's musical clock, for syncing to tempo.
+    #[inline(always)]
+    pub fn transport(&self) -> &Transport {
+        &self.transport
+    }
+    #[inline(always)]
+    pub(crate) fn transport_mut(&mut self) -> &mut Transport {
+        &mut self.transport
+    }
+    pub(crate) fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate as f32;
+        self.transport.set_sample_rate(sample_rate as f32);
+    }
+    #[inline(always)]
+    pub(crate) fn set_frames(&mut self, frames: usize) {
+        self.frames = frames;
+    }
+    /// Takes the sample rate, block size and transport state of an outer
+    /// graph, keeping this context's own transport handle.
+    #[inline(always)]
+    pub(crate) fn follow(&mut self, outer: &AudioContext) {
+        self.sample_rate = outer.sample_rate;
+        self.frames = outer.frames;
+        self.transport.follow(&outer.transport);
+    }
+}
+impl Default for AudioContext {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_RATE)
+    }
+}
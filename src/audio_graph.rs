@@ -1,35 +1,149 @@
-pub trait AudioGraph<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize> {
-    fn next_block(&mut self) -> &Frame<BUFFER_SIZE, CHANNEL_COUNT>;
+pub trait AudioGraph<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize, S: Sample = f32> {
+    fn next_block(&mut self, frames: usize) -> &Frame<BUFFER_SIZE, CHANNEL_COUNT, S>;
     fn invalidate_sort_order(&mut self);
 }
 
+use hashbrown::HashMap;
+use indexmap::IndexSet;
+
+use super::audio_context::AudioContext;
 use super::buffer::{Buffer, Frame};
-use super::node::BoxedNode;
+use super::channel::ChannelMap;
+use super::node::{BoxedNode, Node};
 use super::graph::{DynamicGraph, Graph};
+use super::parallel::{Job, WorkerPool};
+use super::routing::{Merger, Splitter};
+use super::sample::Sample;
+use super::subgraph::Inlet;
+use super::transport::TransportHandle;
 
-pub struct DynamicAudioGraph<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize> {
-    graph: DynamicGraph<BoxedNode<BUFFER_SIZE, CHANNEL_COUNT>>,
-    inputs_buffer: Vec<Frame<BUFFER_SIZE, CHANNEL_COUNT>>,
-    output_buffers: Vec<Frame<BUFFER_SIZE, CHANNEL_COUNT>>,
+pub struct DynamicAudioGraph<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize, S: Sample = f32> {
+    graph: DynamicGraph<BoxedNode<BUFFER_SIZE, CHANNEL_COUNT, S>>,
+    inputs_buffer: Vec<Frame<BUFFER_SIZE, CHANNEL_COUNT, S>>,
+    output_buffers: Vec<Frame<BUFFER_SIZE, CHANNEL_COUNT, S>>,
+    output_channels: Vec<usize>,
+    names: Vec<Option<String>>,
+    inputs: Vec<Option<usize>>,
+    buses: Vec<Bus<BUFFER_SIZE, CHANNEL_COUNT, S>>,
+    channel_maps: HashMap<(usize, usize), ChannelMap<CHANNEL_COUNT>>,
+    sink_buffer: Frame<BUFFER_SIZE, CHANNEL_COUNT, S>,
     sort_order: Vec<usize>,
+    level_order: Vec<usize>,
+    level_bounds: Vec<usize>,
     sink_index: usize,
+    capacity: usize,
+    context: AudioContext,
+    pool: Option<WorkerPool<BUFFER_SIZE, CHANNEL_COUNT, S>>,
 }
 
-impl<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize> DynamicAudioGraph<BUFFER_SIZE, CHANNEL_COUNT> {
+impl<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize, S: Sample> DynamicAudioGraph<BUFFER_SIZE, CHANNEL_COUNT, S> {
     pub fn with_capacity(capacity: usize) -> Self {
         let graph = DynamicGraph::with_capacity(capacity);
         Self {
             graph,
             inputs_buffer: Vec::with_capacity(capacity),
-            output_buffers: vec![[Buffer::<BUFFER_SIZE>::default(); CHANNEL_COUNT]; capacity],
+            output_buffers: vec![[Buffer::<BUFFER_SIZE, S>::default(); CHANNEL_COUNT]; capacity],
+            output_channels: Vec::with_capacity(capacity),
+            names: Vec::with_capacity(capacity),
+            inputs: Vec::new(),
+            buses: Vec::new(),
+            channel_maps: HashMap::new(),
+            sink_buffer: [Buffer::SILENT; CHANNEL_COUNT],
             sort_order: Vec::with_capacity(capacity),
+            level_order: Vec::with_capacity(capacity),
+            level_bounds: Vec::with_capacity(capacity + 1),
             sink_index: 0,
+            capacity,
+            context: AudioContext::default(),
+            pool: None,
         }
     }
 
-    pub fn add_node(&mut self, node: BoxedNode<BUFFER_SIZE, CHANNEL_COUNT>) -> usize {
-        let id = self.graph.add_node(node);
-        id
+    /// Computes independent branches of the graph on `workers` extra
+    /// threads, alongside the thread calling `next_block`. Passing zero
+    /// returns to the serial executor. Both produce the same output.
+    ///
+    /// This spawns threads, so call it before handing the graph to the
+    /// audio callback.
+    pub fn set_worker_threads(&mut self, workers: usize) {
+        self.pool = match workers {
+            0 => None,
+            _ => Some(WorkerPool::new(workers, self.capacity)),
+        };
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.context.set_sample_rate(sample_rate);
+    }
+
+    /// A handle for playing, stopping and moving the graph's transport
+    /// from another thread.
+    pub fn transport(&self) -> TransportHandle {
+        self.context.transport().handle()
+    }
+
+    pub fn context(&self) -> &AudioContext {
+        &self.context
+    }
+
+    /// Adds a node and returns its index.
+    ///
+    /// # Panics
+    /// If the graph already holds the number of nodes it was made with
+    /// capacity for.
+    pub fn add_node(&mut self, node: BoxedNode<BUFFER_SIZE, CHANNEL_COUNT, S>) -> usize {
+        assert!(
+            self.node_count() < self.capacity,
+            "cannot add node: the graph was made with capacity for {} nodes",
+            self.capacity
+        );
+        self.output_channels.push(node.output_channels().min(CHANNEL_COUNT));
+        self.names.push(None);
+        let index = self.graph.add_node(node);
+        self.invalidate_sort_order();
+        index
+    }
+
+    /// The number of channels a node writes.
+    pub fn output_channels(&self, node: usize) -> usize {
+        self.output_channels[node]
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.graph.nodes.len()
+    }
+
+    pub fn node(&self, node: usize) -> &BoxedNode<BUFFER_SIZE, CHANNEL_COUNT, S> {
+        &self.graph.nodes[node]
+    }
+
+    pub fn node_mut(&mut self, node: usize) -> &mut BoxedNode<BUFFER_SIZE, CHANNEL_COUNT, S> {
+        &mut self.graph.nodes[node]
+    }
+
+    /// Names a node, so patches and tools can refer to it by something
+    /// more stable than its index.
+    pub fn set_node_name(&mut self, node: usize, name: &str) {
+        self.names[node] = Some(name.to_string());
+    }
+
+    pub fn node_name(&self, node: usize) -> Option<&str> {
+        self.names[node].as_deref()
+    }
+
+    /// The index of the node called `name`.
+    pub fn node_index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|node_name| node_name.as_deref() == Some(name))
+    }
+
+    /// The sources feeding `node`, in port order.
+    pub fn incoming(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.graph.incoming[node].iter().copied()
+    }
+
+    /// How the channels of the edge from `from` to `to` are mixed.
+    pub fn channel_map(&self, from: usize, to: usize) -> ChannelMap<CHANNEL_COUNT> {
+        self.channel_maps.get(&(from, to)).copied().unwrap_or_default()
     }
 
     pub fn add_edge(&mut self, from: usize, to: usize) {
@@ -42,32 +156,289 @@ impl<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize> DynamicAudioGraph<BUF
         self.invalidate_sort_order();
     }
 
+    /// Adds an edge that folds the source's channels into the
+    /// destination's with `map`, rather than the automatic rules.
+    pub fn add_edge_with_map(&mut self, from: usize, to: usize, map: ChannelMap<CHANNEL_COUNT>) {
+        self.set_channel_map(from, to, map);
+        self.add_edge(from, to);
+    }
+
+    /// Adds a [`Splitter`] for each channel `from` writes and returns
+    /// them in channel order, so every channel can be routed on its own.
+    /// Each splitter takes up a node of the graph's capacity.
+    pub fn split(&mut self, from: usize) -> Vec<usize> {
+        let splitters: Vec<usize> = (0..self.output_channels[from])
+            .map(|channel| self.add_node(Box::new(Splitter::new(channel))))
+            .collect();
+        let edges: Vec<(usize, usize)> = splitters.iter().map(|&splitter| (from, splitter)).collect();
+        self.add_edges(&edges);
+        splitters
+    }
+
+    /// Adds a [`Merger`] whose channels are `sources`, in order, and
+    /// returns it. The merger takes up a node of the graph's capacity.
+    pub fn merge(&mut self, sources: &[usize]) -> usize {
+        let merger = self.add_node(Box::new(Merger::new()));
+        let edges: Vec<(usize, usize)> = sources.iter().map(|&source| (source, merger)).collect();
+        self.add_edges(&edges);
+        merger
+    }
+
+    /// Adds an [`Inlet`] fed by the next input port of the graph and
+    /// returns it. The graph's inputs are passed to
+    /// [`next_block_with_inputs`](Self::next_block_with_inputs), or come
+    /// from the outer graph when it runs as a [`Subgraph`](crate::subgraph::Subgraph).
+    pub fn add_input(&mut self) -> usize {
+        let port = self.inputs.len();
+        let inlet = self.add_node(Box::new(Inlet::new(port)));
+        self.inputs.push(Some(inlet));
+        inlet
+    }
+
+    /// Feeds input `port` into `node`, which should be an [`Inlet`] so
+    /// it leaves the input in place, as when building a graph from a
+    /// patch.
+    pub fn set_input(&mut self, port: usize, node: usize) {
+        if port >= self.inputs.len() {
+            self.inputs.resize(port + 1, None);
+        }
+        self.inputs[port] = Some(node);
+    }
+
+    /// The node fed by each input port, if any.
+    pub fn inputs(&self) -> &[Option<usize>] {
+        &self.inputs
+    }
+
+    /// Changes how the channels of an edge are mixed.
+    pub fn set_channel_map(&mut self, from: usize, to: usize, map: ChannelMap<CHANNEL_COUNT>) {
+        match map {
+            ChannelMap::Auto => self.channel_maps.remove(&(from, to)),
+            map => self.channel_maps.insert((from, to), map),
+        };
+    }
+
     pub fn set_sink_index(&mut self, sink: usize) {
         self.sink_index = sink;
     }
 
+    pub fn sink_index(&self) -> usize {
+        self.sink_index
+    }
+
+    /// Adds an output bus called `name` carrying the output of `node`,
+    /// or moves the bus to `node` if there is one already, and returns
+    /// its index. Every bus is computed each block alongside the sink, so
+    /// stems and separate monitor and main mixes come from one graph.
+    ///
+    /// # Panics
+    /// If there is no node `node`.
+    pub fn add_bus(&mut self, name: &str, node: usize) -> usize {
+        assert!(node < self.node_count(), "cannot add bus `{name}`: there is no node {node}");
+        if let Some(bus) = self.bus_index(name) {
+            self.buses[bus].node = node;
+            return bus;
+        }
+        self.buses.push(Bus {
+            name: name.to_string(),
+            node,
+            buffer: [Buffer::SILENT; CHANNEL_COUNT],
+        });
+        self.buses.len() - 1
+    }
+
+    pub fn bus_count(&self) -> usize {
+        self.buses.len()
+    }
+
+    /// The index of the bus called `name`.
+    pub fn bus_index(&self, name: &str) -> Option<usize> {
+        self.buses.iter().position(|bus| bus.name == name)
+    }
+
+    pub fn bus_name(&self, bus: usize) -> &str {
+        &self.buses[bus].name
+    }
+
+    /// The node a bus carries.
+    pub fn bus_node(&self, bus: usize) -> usize {
+        self.buses[bus].node
+    }
+
+    /// The output of `bus` in the last block, mixed up to
+    /// `CHANNEL_COUNT` channels like the sink. Only the frames asked of
+    /// that block are valid.
+    pub fn bus(&self, bus: usize) -> &Frame<BUFFER_SIZE, CHANNEL_COUNT, S> {
+        let bus = &self.buses[bus];
+        match self.output_channels[bus.node] < CHANNEL_COUNT {
+            true => &bus.buffer,
+            false => &self.output_buffers[bus.node],
+        }
+    }
+
+    /// The order nodes are computed in, sources first. Empty until the
+    /// first edge is added.
+    pub fn sort_order(&self) -> &[usize] {
+        &self.sort_order
+    }
+
     fn invalidate_sort_order(&mut self) {
         match self.graph.topo_sort() {
             Ok(order) => self.sort_order = order,
             Err(_) => panic!("Cycle detected in audio graph"),
         }
+        (self.level_order, self.level_bounds) = self.graph.dependency_levels(&self.sort_order);
+    }
+
+    /// Computes the next `frames` samples of every node and returns the
+    /// sink. Any block size up to `BUFFER_SIZE` can be requested, so a
+    /// host with a different buffer size can be served without rebuilding
+    /// the graph, and a block can be split into smaller ones to apply
+    /// parameter changes on the exact sample they land on. Only the first
+    /// `frames` samples of the returned frame are valid.
+    ///
+    /// A sink with fewer channels than the graph is mixed up to
+    /// `CHANNEL_COUNT` with the automatic rules of [`ChannelMap::Auto`].
+    #[inline(always)]
+    pub fn next_block(&mut self, frames: usize) -> &Frame<BUFFER_SIZE, CHANNEL_COUNT, S> {
+        let frames = frames.min(BUFFER_SIZE);
+        self.context.set_frames(frames);
+        self.context.transport_mut().sync();
+        self.render(frames);
+        self.mix_buses(frames);
+        self.context.transport_mut().advance(frames);
+        self.sink(frames)
+    }
+
+    /// Like [`next_block`](Self::next_block), feeding the graph's
+    /// [inputs](Self::add_input) from `inputs` in port order. Inputs
+    /// without a frame are silent.
+    pub fn next_block_with_inputs(&mut self, frames: usize, inputs: &[Frame<BUFFER_SIZE, CHANNEL_COUNT, S>]) -> &Frame<BUFFER_SIZE, CHANNEL_COUNT, S> {
+        self.feed_inputs(inputs);
+        self.next_block(frames)
+    }
+
+    /// Computes a block of a graph nested inside another, following the
+    /// outer graph's sample rate, block size and transport rather than
+    /// its own.
+    #[inline(always)]
+    pub(crate) fn next_block_nested(&mut self, ctx: &AudioContext, inputs: &[Frame<BUFFER_SIZE, CHANNEL_COUNT, S>]) -> &Frame<BUFFER_SIZE, CHANNEL_COUNT, S> {
+        self.context.follow(ctx);
+        self.feed_inputs(inputs);
+        self.render(ctx.frames());
+        self.mix_buses(ctx.frames());
+        self.sink(ctx.frames())
+    }
+
+    #[inline(always)]
+    fn feed_inputs(&mut self, inputs: &[Frame<BUFFER_SIZE, CHANNEL_COUNT, S>]) {
+        for (port, &node) in self.inputs.iter().enumerate() {
+            let Some(node) = node else { continue };
+            self.output_buffers[node] = inputs.get(port).copied().unwrap_or([Buffer::SILENT; CHANNEL_COUNT]);
+        }
     }
 
+    /// Runs every node once, in dependency order.
     #[inline(always)]
-    pub fn next_block(&mut self) -> &Frame<BUFFER_SIZE, CHANNEL_COUNT> {
-        for &node_index in &self.sort_order {
-            let node = &mut self.graph.nodes[node_index];
-            let inputs = &self.graph.incoming[node_index];
-
-            self.inputs_buffer.clear();
-            self.inputs_buffer.reserve(inputs.len());
-            for &src in inputs {
-                self.inputs_buffer.push(self.output_buffers[src]);
+    fn render(&mut self, frames: usize) {
+        let routing = Routing {
+            incoming: &self.graph.incoming,
+            output_channels: &self.output_channels,
+            channel_maps: &self.channel_maps,
+        };
+        match &self.pool {
+            Some(pool) => {
+                let job = Job {
+                    ctx: &self.context,
+                    routing,
+                    nodes: self.graph.nodes.as_mut_ptr(),
+                    outputs: self.output_buffers.as_mut_ptr(),
+                    order: &self.level_order,
+                    bounds: &self.level_bounds,
+                };
+                pool.run(&job, &mut self.inputs_buffer);
             }
+            None => {
+                for &node_index in &self.sort_order {
+                    let node = &mut self.graph.nodes[node_index];
+                    let outputs = &self.output_buffers;
+                    gather_inputs(&routing, node_index, &**node, frames, |src| &outputs[src], &mut self.inputs_buffer);
+
+                    node.process(&self.context, &self.inputs_buffer, &mut self.output_buffers[node_index]);
+                }
+            }
+        }
+    }
 
-            node.process(&self.inputs_buffer, &mut self.output_buffers[node_index]);
+    /// Mixes buses carrying fewer channels than the graph up to
+    /// `CHANNEL_COUNT`. The others are read straight from their node.
+    #[inline(always)]
+    fn mix_buses(&mut self, frames: usize) {
+        for bus in &mut self.buses {
+            let channels = self.output_channels[bus.node];
+            if channels < CHANNEL_COUNT {
+                ChannelMap::Auto.apply(&self.output_buffers[bus.node], channels, &mut bus.buffer, CHANNEL_COUNT, frames);
+            }
         }
+    }
 
-        &self.output_buffers[self.sink_index]
+    /// The sink's output, mixed up to `CHANNEL_COUNT` channels.
+    #[inline(always)]
+    fn sink(&mut self, frames: usize) -> &Frame<BUFFER_SIZE, CHANNEL_COUNT, S> {
+        let sink = &self.output_buffers[self.sink_index];
+        match self.output_channels.get(self.sink_index) {
+            Some(&channels) if channels < CHANNEL_COUNT => {
+                ChannelMap::Auto.apply(sink, channels, &mut self.sink_buffer, CHANNEL_COUNT, frames);
+                &self.sink_buffer
+            }
+            _ => sink,
+        }
+    }
+}
+
+/// A named output of the graph besides the sink.
+struct Bus<const N: usize, const C: usize, S: Sample> {
+    name: String,
+    node: usize,
+    buffer: Frame<N, C, S>,
+}
+
+/// The parts of the graph needed to feed a node its inputs.
+pub(crate) struct Routing<'a, const C: usize> {
+    pub incoming: &'a [IndexSet<usize>],
+    pub output_channels: &'a [usize],
+    pub channel_maps: &'a HashMap<(usize, usize), ChannelMap<C>>,
+}
+
+/// Copies the outputs of a node's sources into its input buffer, in the
+/// order the edges were added, mixing each one to the channel count the
+/// node expects on that port. A node without sources is given one
+/// silent input, so an effect left unconnected outputs silence.
+#[inline(always)]
+pub(crate) fn gather_inputs<'a, const N: usize, const C: usize, S: Sample>(
+    routing: &Routing<'_, C>,
+    node_index: usize,
+    node: &dyn Node<N, C, S>,
+    frames: usize,
+    output_of: impl Fn(usize) -> &'a Frame<N, C, S>,
+    inputs_buffer: &mut Vec<Frame<N, C, S>>,
+) {
+    let sources = &routing.incoming[node_index];
+    inputs_buffer.clear();
+    inputs_buffer.reserve(sources.len().max(1));
+    if sources.is_empty() {
+        inputs_buffer.push([Buffer::SILENT; C]);
+    }
+    for (port, &src) in sources.iter().enumerate() {
+        let source = output_of(src);
+        inputs_buffer.push(*source);
+
+        let src_channels = routing.output_channels[src];
+        let dst_channels = node.input_channels(port).min(C);
+        let map = routing.channel_maps.get(&(src, node_index)).unwrap_or(&ChannelMap::Auto);
+        if *map != ChannelMap::Auto || src_channels != dst_channels {
+            let input = inputs_buffer.last_mut().unwrap();
+            map.apply(source, src_channels, input, dst_channels, frames);
+        }
     }
 }
@@ -1,51 +1,160 @@
 
 use core::fmt;
-use core::ops::{Deref, DerefMut};
+use core::ops::{Add, AddAssign, Deref, DerefMut, Mul, MulAssign, Sub, SubAssign};
 
-pub type Frame<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize> = [Buffer<BUFFER_SIZE>; CHANNEL_COUNT];
+use crate::sample::Sample;
+use crate::simd;
+
+pub type Frame<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize, S = f32> = [Buffer<BUFFER_SIZE, S>; CHANNEL_COUNT];
+
+/// A single channel of samples, aligned so that the vectorised
+/// primitives in [`crate::simd`] can load whole lanes at a time.
 #[derive(Clone, Copy)]
-pub struct Buffer<const BUFFER_SIZE: usize> {
-    data: [f32; BUFFER_SIZE],
+#[repr(C, align(32))]
+pub struct Buffer<const BUFFER_SIZE: usize, S: Sample = f32> {
+    data: [S; BUFFER_SIZE],
 }
 
-impl<const N: usize> Buffer<N> {
+impl<const N: usize, S: Sample> Buffer<N, S> {
     /// A silent **Buffer**.
-    pub const SILENT: Self = Buffer { data: [0.0; N] };
+    pub const SILENT: Self = Buffer { data: [S::ZERO; N] };
+}
+
+impl<const N: usize, S: Sample> Buffer<N, S> {
+    /// Adds `other` into this buffer, scaled by `gain`.
+    #[inline(always)]
+    pub fn mix_from(&mut self, other: &Self, gain: S) {
+        simd::mul_add(self, other, gain);
+    }
+
+    /// Scales the first `frames` samples by a gain moving linearly from
+    /// `start` to `end`, which avoids the zipper noise of jumping straight
+    /// to a new gain. `end` is reached on the sample after the block, so
+    /// consecutive ramps join up without repeating a value.
+    #[inline(always)]
+    pub fn apply_gain_ramp(&mut self, frames: usize, start: S, end: S) {
+        let frames = frames.min(N);
+        let step = (end - start) / S::from_f64(frames.max(1) as f64);
+        let mut gain = start;
+        for sample in self.data[..frames].iter_mut() {
+            *sample *= gain;
+            gain += step;
+        }
+    }
+
+    /// The largest absolute value of the first `frames` samples.
+    #[inline(always)]
+    pub fn peak(&self, frames: usize) -> S {
+        self.data[..frames.min(N)].iter().fold(S::ZERO, |peak, sample| peak.max(sample.abs()))
+    }
+
+    /// The root mean square of the first `frames` samples.
+    #[inline(always)]
+    pub fn rms(&self, frames: usize) -> S {
+        let frames = frames.min(N);
+        if frames == 0 {
+            return S::ZERO;
+        }
+        let sum: S = self.data[..frames].iter().map(|&sample| sample * sample).sum();
+        (sum / S::from_f64(frames as f64)).sqrt()
+    }
+}
+
+/// Writes `frame` into `output` one sample per channel at a time, the
+/// layout audio devices expect. Stops at whichever runs out first.
+#[inline(always)]
+pub fn interleave<const N: usize, const C: usize, S: Sample>(frame: &Frame<N, C, S>, output: &mut [S]) {
+    for (n, chunk) in output.chunks_mut(C).take(N).enumerate() {
+        for (sample, buffer) in chunk.iter_mut().zip(frame) {
+            *sample = buffer[n];
+        }
+    }
 }
 
-impl<const N: usize> Default for Buffer<N> {
+/// The inverse of [`interleave`], splitting channel interleaved samples
+/// into a frame.
+#[inline(always)]
+pub fn deinterleave<const N: usize, const C: usize, S: Sample>(input: &[S], frame: &mut Frame<N, C, S>) {
+    for (n, chunk) in input.chunks(C).take(N).enumerate() {
+        for (sample, buffer) in chunk.iter().zip(frame.iter_mut()) {
+            buffer[n] = *sample;
+        }
+    }
+}
+
+impl<const N: usize, S: Sample> Default for Buffer<N, S> {
     fn default() -> Self {
         Self::SILENT
     }
 }
 
-impl<const N: usize> From<[f32; N]> for Buffer<N> {
-    fn from(data: [f32; N]) -> Self {
+impl<const N: usize, S: Sample> From<[S; N]> for Buffer<N, S> {
+    fn from(data: [S; N]) -> Self {
         Buffer { data }
     }
 }
 
-impl<const N: usize> fmt::Debug for Buffer<N> {
+impl<const N: usize, S: Sample> fmt::Debug for Buffer<N, S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Debug::fmt(&self.data[..], f)
     }
 }
 
-impl<const N: usize> PartialEq for Buffer<N> {
+impl<const N: usize, S: Sample> PartialEq for Buffer<N, S> {
     fn eq(&self, other: &Self) -> bool {
         self[..] == other[..]
     }
 }
 
-impl<const N: usize> Deref for Buffer<N> {
-    type Target = [f32];
+impl<const N: usize, S: Sample> Deref for Buffer<N, S> {
+    type Target = [S];
     fn deref(&self) -> &Self::Target {
         &self.data[..]
     }
 }
 
-impl<const N: usize> DerefMut for Buffer<N> {
+impl<const N: usize, S: Sample> DerefMut for Buffer<N, S> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.data[..]
     }
-}
\ No newline at end of file
+}
+
+macro_rules! impl_buffer_op {
+    ($op:ident, $op_fn:ident, $assign:ident, $assign_fn:ident, $simd_fn:path) => {
+        impl<const N: usize, S: Sample> $assign for Buffer<N, S> {
+            #[inline(always)]
+            fn $assign_fn(&mut self, rhs: Self) {
+                $simd_fn(self, &rhs);
+            }
+        }
+
+        impl<const N: usize, S: Sample> $op for Buffer<N, S> {
+            type Output = Self;
+            #[inline(always)]
+            fn $op_fn(mut self, rhs: Self) -> Self {
+                $simd_fn(&mut self, &rhs);
+                self
+            }
+        }
+    };
+}
+
+impl_buffer_op!(Add, add, AddAssign, add_assign, simd::add);
+impl_buffer_op!(Sub, sub, SubAssign, sub_assign, simd::sub);
+impl_buffer_op!(Mul, mul, MulAssign, mul_assign, simd::mul);
+
+impl<const N: usize, S: Sample> MulAssign<S> for Buffer<N, S> {
+    #[inline(always)]
+    fn mul_assign(&mut self, rhs: S) {
+        simd::scale(self, rhs);
+    }
+}
+
+impl<const N: usize, S: Sample> Mul<S> for Buffer<N, S> {
+    type Output = Self;
+    #[inline(always)]
+    fn mul(mut self, rhs: S) -> Self {
+        simd::scale(&mut self, rhs);
+        self
+    }
+}
@@ -0,0 +1,84 @@
+use crate::buffer::Frame;
+use crate::sample::Sample;
+use crate::simd;
+
+/// How the channels of a source are folded into the channels a node
+/// expects on one of its inputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ChannelMap<const C: usize> {
+    /// Picks a rule from the channel counts on either side of the edge:
+    /// * equal counts are copied straight across.
+    /// * a mono source is duplicated to every channel.
+    /// * a mono destination receives the sum of every source channel.
+    /// * anything else copies the channels both sides share, and leaves
+    ///   the rest silent. Use a matrix to fold surround down properly.
+    #[default]
+    Auto,
+    /// `gains[output][input]` is how much of each source channel lands in
+    /// each destination channel.
+    Matrix([[f32; C]; C]),
+}
+
+impl<const C: usize> ChannelMap<C> {
+    /// A matrix passing every channel through unchanged.
+    pub fn identity() -> Self {
+        let mut gains = [[0.0; C]; C];
+        for (c, row) in gains.iter_mut().enumerate() {
+            row[c] = 1.0;
+        }
+        Self::Matrix(gains)
+    }
+
+    /// Writes the first `frames` samples of the `src_channels` channels of
+    /// `src` into the `dst_channels` channels of `dst`.
+    #[inline(always)]
+    pub fn apply<const N: usize, S: Sample>(
+        &self,
+        src: &Frame<N, C, S>,
+        src_channels: usize,
+        dst: &mut Frame<N, C, S>,
+        dst_channels: usize,
+        frames: usize,
+    ) {
+        let (src_channels, dst_channels) = (src_channels.min(C), dst_channels.min(C));
+        match self {
+            Self::Auto if src_channels == dst_channels => {
+                for (dst, src) in dst[..dst_channels].iter_mut().zip(&src[..src_channels]) {
+                    simd::copy(&mut dst[..frames], src);
+                }
+            }
+            Self::Auto if src_channels == 1 => {
+                for dst in dst[..dst_channels].iter_mut() {
+                    simd::copy(&mut dst[..frames], &src[0]);
+                }
+            }
+            Self::Auto if dst_channels == 1 => {
+                let dst = &mut dst[0][..frames];
+                simd::fill(dst, S::ZERO);
+                for src in &src[..src_channels] {
+                    simd::add(dst, src);
+                }
+            }
+            Self::Auto => {
+                let shared = src_channels.min(dst_channels);
+                for (dst, src) in dst[..shared].iter_mut().zip(&src[..shared]) {
+                    simd::copy(&mut dst[..frames], src);
+                }
+                for dst in dst[shared..dst_channels].iter_mut() {
+                    simd::fill(&mut dst[..frames], S::ZERO);
+                }
+            }
+            Self::Matrix(gains) => {
+                for (dst, row) in dst[..dst_channels].iter_mut().zip(gains) {
+                    let dst = &mut dst[..frames];
+                    simd::fill(dst, S::ZERO);
+                    for (src, &gain) in src[..src_channels].iter().zip(row) {
+                        if gain != 0.0 {
+                            simd::mul_add(dst, src, S::from_f32(gain));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::path::Path;
+
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::fft::{Complex, Fft};
+use crate::node::Node;
+use crate::sample::Sample;
+use crate::wav::{read_wav, WavError};
+
+#[derive(Debug)]
+pub enum ConvolverError {
+    Wav(WavError),
+    /// Only one, two or four channel responses can be loaded.
+    ChannelLayout(usize),
+    /// The response was recorded at a different rate than the graph runs
+    /// at.
+    SampleRate { response: u32, graph: u32 },
+}
+
+impl From<WavError> for ConvolverError {
+    fn from(error: WavError) -> Self {
+        ConvolverError::Wav(error)
+    }
+}
+
+/// An impulse response from one input channel to one output channel.
+pub struct IrPath<'a> {
+    pub input: usize,
+    pub output: usize,
+    pub ir: &'a [f32],
+}
+
+struct Input<S: Sample> {
+    /// The last partition of samples, written twice so the newest
+    /// `partition` samples are always contiguous.
+    history: Vec<S>,
+    /// The previous and current partition, the window overlap-save
+    /// transforms.
+    window: Vec<S>,
+    /// Spectra of the most recent windows, newest at `Convolver::slot`.
+    spectra: Vec<Vec<Complex<S>>>,
+}
+
+struct IrSpectra<S: Sample> {
+    input: usize,
+    output: usize,
+    /// The first partition of the response, reversed, convolved directly
+    /// so it adds no latency.
+    head: Vec<S>,
+    /// Spectra of every later partition.
+    tail: Vec<Vec<Complex<S>>>,
+}
+
+struct Output<S: Sample> {
+    /// The tail's contribution to the partition being played.
+    tail: Vec<S>,
+}
+
+/// Convolves the input with impulse responses, for cabinet simulation or
+/// convolution reverb. Responses are split into partitions: the first is
+/// convolved sample by sample so it adds no latency, and the rest with
+/// uniformly partitioned overlap-save FFT convolution, computed whenever
+/// a partition of input has been gathered.
+///
+/// Any number of paths between input and output channels can be given,
+/// so mono, per channel stereo and true stereo responses all work.
+/// Everything is allocated on construction.
+pub struct Convolver<const N: usize, const C: usize, S: Sample = f32> {
+    partition: usize,
+    fft: Fft<S>,
+    inputs: Vec<Input<S>>,
+    paths: Vec<IrSpectra<S>>,
+    outputs: Vec<Output<S>>,
+    scratch: Vec<Complex<S>>,
+    accumulator: Vec<Complex<S>>,
+    position: usize,
+    slot: usize,
+}
+
+impl<const N: usize, const C: usize, S: Sample> Convolver<N, C, S> {
+    /// Builds a convolver from any set of paths. `partition` is rounded up
+    /// to a power of two. Larger partitions make the FFT part cheaper, but
+    /// the directly convolved first partition costs `partition`
+    /// multiplies per sample and path.
+    pub fn new(paths: &[IrPath], partition: usize) -> Self {
+        let partition = partition.max(1).next_power_of_two();
+        let size = partition * 2;
+        let fft = Fft::new(size);
+
+        let mut depth = [1; C];
+        let paths: Vec<IrSpectra<S>> = paths
+            .iter()
+            .filter(|path| path.input < C && path.output < C)
+            .map(|path| {
+                let head_len = path.ir.len().min(partition);
+                let mut head = vec![S::ZERO; partition];
+                for (tap, &value) in head.iter_mut().rev().zip(&path.ir[..head_len]) {
+                    *tap = S::from_f32(value);
+                }
+                let tail: Vec<Vec<Complex<S>>> = path.ir[head_len..]
+                    .chunks(partition)
+                    .map(|taps| {
+                        let mut spectrum = vec![Complex::ZERO; size];
+                        for (bin, &tap) in spectrum.iter_mut().zip(taps) {
+                            bin.re = S::from_f32(tap);
+                        }
+                        fft.forward(&mut spectrum);
+                        spectrum
+                    })
+                    .collect();
+                depth[path.input] = depth[path.input].max(tail.len());
+                IrSpectra {
+                    input: path.input,
+                    output: path.output,
+                    head,
+                    tail,
+                }
+            })
+            .collect();
+
+        Self {
+            partition,
+            inputs: depth
+                .iter()
+                .map(|&depth| Input {
+                    history: vec![S::ZERO; partition * 2],
+                    window: vec![S::ZERO; size],
+                    spectra: vec![vec![Complex::ZERO; size]; depth],
+                })
+                .collect(),
+            paths,
+            outputs: (0..C).map(|_| Output { tail: vec![S::ZERO; partition] }).collect(),
+            scratch: vec![Complex::ZERO; size],
+            accumulator: vec![Complex::ZERO; size],
+            fft,
+            position: 0,
+            slot: 0,
+        }
+    }
+
+    /// Applies the same response to every channel.
+    pub fn mono(ir: &[f32]) -> Self {
+        let paths: Vec<IrPath> = (0..C).map(|c| IrPath { input: c, output: c, ir }).collect();
+        Self::new(&paths, N)
+    }
+
+    /// Applies a separate response to the left and right channels.
+    pub fn stereo(left: &[f32], right: &[f32]) -> Self {
+        Self::new(
+            &[
+                IrPath { input: 0, output: 0, ir: left },
+                IrPath { input: 1, output: 1, ir: right },
+            ],
+            N,
+        )
+    }
+
+    /// Convolves each input channel into both outputs, as recorded by a
+    /// true stereo response: left to left, left to right, right to left
+    /// and right to right.
+    pub fn true_stereo(ll: &[f32], lr: &[f32], rl: &[f32], rr: &[f32]) -> Self {
+        Self::new(
+            &[
+                IrPath { input: 0, output: 0, ir: ll },
+                IrPath { input: 0, output: 1, ir: lr },
+                IrPath { input: 1, output: 0, ir: rl },
+                IrPath { input: 1, output: 1, ir: rr },
+            ],
+            N,
+        )
+    }
+
+    /// Loads a response from a WAV file for a graph running at
+    /// `sample_rate`, which the response must be recorded at. One channel
+    /// is used as a mono response, two as a stereo pair, and four as true
+    /// stereo in the order of [`Convolver::true_stereo`].
+    pub fn from_wav(path: impl AsRef<Path>, sample_rate: u32) -> Result<Self, ConvolverError> {
+        let wav = read_wav(path)?;
+        if wav.sample_rate != sample_rate {
+            return Err(ConvolverError::SampleRate { response: wav.sample_rate, graph: sample_rate });
+        }
+        Ok(match wav.channels.as_slice() {
+            [ll, lr, rl, rr] => Self::true_stereo(ll, lr, rl, rr),
+            [left, right] => Self::stereo(left, right),
+            [mono] => Self::mono(mono),
+            channels => return Err(ConvolverError::ChannelLayout(channels.len())),
+        })
+    }
+
+    /// Delays, in samples: the convolver adds none.
+    pub fn latency(&self) -> usize {
+        0
+    }
+
+    /// Runs the FFT convolution once a full partition of input has been
+    /// gathered, producing the tail of the next partition.
+    fn convolve_tail(&mut self) {
+        let partition = self.partition;
+        for input in self.inputs.iter_mut() {
+            let depth = input.spectra.len();
+            let spectrum = &mut input.spectra[self.slot % depth];
+            for (bin, &sample) in spectrum.iter_mut().zip(&input.window) {
+                *bin = Complex::new(sample, S::ZERO);
+            }
+            self.fft.forward(spectrum);
+            input.window.copy_within(partition.., 0);
+        }
+
+        for (channel, output) in self.outputs.iter_mut().enumerate() {
+            self.accumulator.fill(Complex::ZERO);
+            let mut used = false;
+            for path in self.paths.iter().filter(|path| path.output == channel) {
+                let spectra = &self.inputs[path.input].spectra;
+                let depth = spectra.len();
+                for (age, ir) in path.tail.iter().enumerate() {
+                    let input = &spectra[(self.slot + depth - age) % depth];
+                    for ((sum, &x), &h) in self.accumulator.iter_mut().zip(input).zip(ir) {
+                        *sum = *sum + x * h;
+                    }
+                    used = true;
+                }
+            }
+            if !used {
+                continue;
+            }
+            self.scratch.copy_from_slice(&self.accumulator);
+            self.fft.inverse(&mut self.scratch);
+            for (out, bin) in output.tail.iter_mut().zip(&self.scratch[partition..]) {
+                *out = bin.re;
+            }
+        }
+        self.slot += 1;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Convolver<N, C, S> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let input = &inputs[0];
+        let partition = self.partition;
+
+        for n in 0..ctx.frames() {
+            let position = self.position;
+            for (c, state) in self.inputs.iter_mut().enumerate() {
+                let sample = input[c][n];
+                state.history[position] = sample;
+                state.history[position + partition] = sample;
+                state.window[partition + position] = sample;
+            }
+            for (c, out) in output.iter_mut().enumerate() {
+                out[n] = self.outputs[c].tail[position];
+            }
+            for path in &self.paths {
+                let history = &self.inputs[path.input].history[position + 1..position + 1 + partition];
+                let mut sum = S::ZERO;
+                for (&tap, &sample) in path.head.iter().zip(history) {
+                    sum += tap * sample;
+                }
+                output[path.output][n] += sum;
+            }
+
+            self.position += 1;
+            if self.position == partition {
+                self.position = 0;
+                self.convolve_tail();
+            }
+        }
+    }
+}
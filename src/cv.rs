@@ -0,0 +1,527 @@
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::node::Node;
+use crate::param::{ParamError, Params, Value};
+use crate::sample::Sample;
+use crate::sequencer::Edge;
+
+/// Where a utility node takes one of its operands from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operand {
+    /// The same channel of the input on this port, or silence if nothing
+    /// is connected there.
+    Input(usize),
+    /// A fixed value.
+    Value(f32),
+}
+
+impl Operand {
+    #[inline(always)]
+    pub fn read<const N: usize, const C: usize, S: Sample>(&self, inputs: &[Frame<N, C, S>], channel: usize, n: usize) -> S {
+        match *self {
+            Operand::Input(port) => inputs.get(port).map_or(S::ZERO, |input| input[channel][n]),
+            Operand::Value(value) => S::from_f32(value),
+        }
+    }
+    /// Saved as a number, or as `in0`, `in1`... for an input port.
+    pub fn to_value(&self) -> Value {
+        match *self {
+            Operand::Input(port) => Value::Text(format!("in{port}")),
+            Operand::Value(value) => value.into(),
+        }
+    }
+    pub fn from_value(value: &Value) -> Result<Self, ParamError> {
+        match value {
+            Value::Number(_) => Ok(Operand::Value(value.float()?)),
+            _ => value.text()?
+                .strip_prefix("in")
+                .and_then(|port| port.parse().ok())
+                .map(Operand::Input)
+                .ok_or(ParamError::Invalid),
+        }
+    }
+}
+
+impl Default for Operand {
+    fn default() -> Self {
+        Operand::Input(0)
+    }
+}
+
+/// A steady mono signal, for offsets, fixed gains and DC.
+pub struct Constant<const N: usize> {
+    value: f32,
+}
+
+impl<const N: usize> Constant<N> {
+    pub fn new(value: f32) -> Self {
+        Self { value }
+    }
+    pub fn set_value(&mut self, value: f32) {
+        self.value = value;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Constant<N> {
+    fn process(&mut self, ctx: &AudioContext, _: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        if let Some(out) = output.first_mut() {
+            out[..ctx.frames()].fill(S::from_f32(self.value));
+        }
+    }
+
+    fn output_channels(&self) -> usize {
+        1
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("constant")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("value", self.value)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "value" => self.set_value(value.float()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Adds every input together, plus an offset. Unlike a
+/// [`Mixer`](crate::mixer::Mixer) nothing is panned or averaged.
+#[derive(Default)]
+pub struct Sum<const N: usize> {
+    offset: f32,
+}
+
+impl<const N: usize> Sum<N> {
+    pub fn new(offset: f32) -> Self {
+        Self { offset }
+    }
+    pub fn set_offset(&mut self, offset: f32) {
+        self.offset = offset;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Sum<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        for (c, out) in output.iter_mut().enumerate() {
+            for n in 0..ctx.frames() {
+                out[n] = inputs.iter().fold(S::from_f32(self.offset), |sum, input| sum + input[c][n]);
+            }
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("sum")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("offset", self.offset)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "offset" => self.set_offset(value.float()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Defines a node combining two operands sample by sample.
+macro_rules! binary_node {
+    ($(#[$doc:meta])* $name:ident, $type_name:literal, |$a:ident, $b:ident| $body:expr) => {
+        $(#[$doc])*
+        pub struct $name<const N: usize> {
+            a: Operand,
+            b: Operand,
+        }
+
+        impl<const N: usize> $name<N> {
+            pub fn new(a: Operand, b: Operand) -> Self {
+                Self { a, b }
+            }
+            pub fn set_a(&mut self, a: Operand) {
+                self.a = a;
+            }
+            pub fn set_b(&mut self, b: Operand) {
+                self.b = b;
+            }
+        }
+
+        impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for $name<N> {
+            fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+                for (c, out) in output.iter_mut().enumerate() {
+                    for n in 0..ctx.frames() {
+                        let ($a, $b): (S, S) = (self.a.read(inputs, c, n), self.b.read(inputs, c, n));
+                        out[n] = $body;
+                    }
+                }
+            }
+
+            fn type_name(&self) -> Option<&'static str> {
+                Some($type_name)
+            }
+
+            fn params(&self) -> Params {
+                Params::new().with("a", self.a.to_value()).with("b", self.b.to_value())
+            }
+
+            fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+                match name {
+                    "a" => self.set_a(Operand::from_value(value)?),
+                    "b" => self.set_b(Operand::from_value(value)?),
+                    _ => return Err(ParamError::Unknown),
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+binary_node!(
+    /// Multiplies two operands: a VCA with a gain, or ring modulation with
+    /// two signals.
+    Multiply,
+    "multiply",
+    |a, b| a * b
+);
+binary_node!(
+    /// The smaller of two operands.
+    Min,
+    "min",
+    |a, b| a.min(b)
+);
+binary_node!(
+    /// The larger of two operands.
+    Max,
+    "max",
+    |a, b| a.max(b)
+);
+
+/// Scales the first input and then offsets it.
+pub struct OffsetScale<const N: usize> {
+    scale: Operand,
+    offset: Operand,
+}
+
+impl<const N: usize> OffsetScale<N> {
+    pub fn new(scale: Operand, offset: Operand) -> Self {
+        Self { scale, offset }
+    }
+    pub fn set_scale(&mut self, scale: Operand) {
+        self.scale = scale;
+    }
+    pub fn set_offset(&mut self, offset: Operand) {
+        self.offset = offset;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for OffsetScale<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        for (c, out) in output.iter_mut().enumerate() {
+            for n in 0..ctx.frames() {
+                let x = Operand::Input(0).read(inputs, c, n);
+                out[n] = x * self.scale.read(inputs, c, n) + self.offset.read(inputs, c, n);
+            }
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("offset_scale")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("scale", self.scale.to_value())
+            .with("offset", self.offset.to_value())
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "scale" => self.set_scale(Operand::from_value(value)?),
+            "offset" => self.set_offset(Operand::from_value(value)?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Maps the first input linearly from one range to another, such as a
+/// bipolar LFO onto a cutoff range in Hz.
+pub struct RangeMap<const N: usize> {
+    from: (f32, f32),
+    to: (f32, f32),
+    clamp: bool,
+}
+
+impl<const N: usize> RangeMap<N> {
+    /// With `clamp`, inputs outside `from` stay within `to`.
+    pub fn new(from: (f32, f32), to: (f32, f32), clamp: bool) -> Self {
+        Self { from, to, clamp }
+    }
+    pub fn set_ranges(&mut self, from: (f32, f32), to: (f32, f32)) {
+        self.from = from;
+        self.to = to;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for RangeMap<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let span = self.from.1 - self.from.0;
+        let scale = match span {
+            0.0 => 0.0,
+            span => (self.to.1 - self.to.0) / span,
+        };
+        let (low, high) = (self.to.0.min(self.to.1), self.to.0.max(self.to.1));
+        for (c, out) in output.iter_mut().enumerate() {
+            for n in 0..ctx.frames() {
+                let x = Operand::Input(0).read::<N, C, S>(inputs, c, n).to_f32();
+                let mapped = self.to.0 + (x - self.from.0) * scale;
+                out[n] = S::from_f32(if self.clamp { mapped.clamp(low, high) } else { mapped });
+            }
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("range_map")
+    }
+
+    /// Each range is saved as a list of its two ends.
+    fn params(&self) -> Params {
+        Params::new()
+            .with("from", Value::from_floats([self.from.0, self.from.1]))
+            .with("to", Value::from_floats([self.to.0, self.to.1]))
+            .with("clamp", self.clamp)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        let range = |value: &Value| match value.floats()?[..] {
+            [low, high] => Ok((low, high)),
+            _ => Err(ParamError::Invalid),
+        };
+        match name {
+            "from" => self.set_ranges(range(value)?, self.to),
+            "to" => self.set_ranges(self.from, range(value)?),
+            "clamp" => self.clamp = value.bool()?,
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Rectifies the first input.
+#[derive(Default)]
+pub struct Abs<const N: usize>;
+
+impl<const N: usize> Abs<N> {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Abs<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        for (c, out) in output.iter_mut().enumerate() {
+            for n in 0..ctx.frames() {
+                out[n] = Operand::Input(0).read(inputs, c, n).abs();
+            }
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("abs")
+    }
+}
+
+/// Holds the first input each time the trigger on the second input
+/// rises above zero.
+pub struct SampleAndHold<const N: usize, const C: usize, S: Sample = f32> {
+    edge: Edge,
+    held: [S; C],
+}
+
+impl<const N: usize, const C: usize, S: Sample> SampleAndHold<N, C, S> {
+    pub fn new() -> Self {
+        Self {
+            edge: Edge::default(),
+            held: [S::ZERO; C],
+        }
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Default for SampleAndHold<N, C, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for SampleAndHold<N, C, S> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        for n in 0..ctx.frames() {
+            let trigger = Operand::Input(1).read::<N, C, S>(inputs, 0, n).to_f32();
+            if self.edge.rising(trigger) {
+                for (c, held) in self.held.iter_mut().enumerate() {
+                    *held = Operand::Input(0).read(inputs, c, n);
+                }
+            }
+            for (out, &held) in output.iter_mut().zip(&self.held) {
+                out[n] = held;
+            }
+        }
+    }
+
+    fn input_channels(&self, port: usize) -> usize {
+        match port {
+            1 => 1,
+            _ => C,
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("sample_and_hold")
+    }
+}
+
+/// Limits how fast the first input can change, turning steps into ramps
+/// for portamento or smoothing stepped modulation.
+pub struct Slew<const N: usize, const C: usize, S: Sample = f32> {
+    rise_ms: f32,
+    fall_ms: f32,
+    current: [S; C],
+}
+
+impl<const N: usize, const C: usize, S: Sample> Slew<N, C, S> {
+    /// The times, in milliseconds, to rise and fall by one unit.
+    pub fn new(rise_ms: f32, fall_ms: f32) -> Self {
+        Self {
+            rise_ms: rise_ms.max(0.0),
+            fall_ms: fall_ms.max(0.0),
+            current: [S::ZERO; C],
+        }
+    }
+    pub fn set_rise(&mut self, ms: f32) {
+        self.rise_ms = ms.max(0.0);
+    }
+    pub fn set_fall(&mut self, ms: f32) {
+        self.fall_ms = ms.max(0.0);
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Slew<N, C, S> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let step = |ms: f32| match ms {
+            0.0 => S::from_f32(f32::MAX),
+            ms => S::from_f32(1_000.0 / (ms * ctx.get_sample_rate())),
+        };
+        let (rise, fall) = (step(self.rise_ms), step(self.fall_ms));
+        for (c, out) in output.iter_mut().enumerate() {
+            let current = &mut self.current[c];
+            for n in 0..ctx.frames() {
+                let target = Operand::Input(0).read(inputs, c, n);
+                *current = (target - *current).clamp(-fall, rise) + *current;
+                out[n] = *current;
+            }
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("slew")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("rise", self.rise_ms).with("fall", self.fall_ms)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "rise" => self.set_rise(value.float()?),
+            "fall" => self.set_fall(value.float()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Which notes of the octave a [`Quantizer`] snaps to, as a bit per
+/// semitone above the root.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scale(pub u16);
+
+impl Scale {
+    pub const CHROMATIC: Scale = Scale(0b1111_1111_1111);
+    pub const MAJOR: Scale = Scale(0b1010_1011_0101);
+    pub const MINOR: Scale = Scale(0b0101_1010_1101);
+    pub const MAJOR_PENTATONIC: Scale = Scale(0b0010_1001_0101);
+    pub const MINOR_PENTATONIC: Scale = Scale(0b0100_1010_1001);
+
+    #[inline(always)]
+    pub fn contains(&self, semitone: i32) -> bool {
+        self.0 & (1 << semitone.rem_euclid(12)) != 0
+    }
+}
+
+/// Snaps the first input, in semitones such as MIDI note numbers, to the
+/// nearest note of a scale.
+pub struct Quantizer<const N: usize> {
+    scale: Scale,
+    root: i32,
+}
+
+impl<const N: usize> Quantizer<N> {
+    /// `root` is the note the scale starts on, `0` for C.
+    pub fn new(scale: Scale, root: i32) -> Self {
+        Self { scale, root }
+    }
+    pub fn set_scale(&mut self, scale: Scale, root: i32) {
+        self.scale = scale;
+        self.root = root;
+    }
+    #[inline(always)]
+    pub fn quantize(&self, pitch: f32) -> f32 {
+        if self.scale.0 & 0xFFF == 0 {
+            return pitch;
+        }
+        let nearest = pitch.round() as i32;
+        (0..=6)
+            .flat_map(|distance| [nearest - distance, nearest + distance])
+            .filter(|&note| self.scale.contains(note - self.root))
+            .min_by(|&a, &b| (a as f32 - pitch).abs().total_cmp(&(b as f32 - pitch).abs()))
+            .map_or(pitch, |note| note as f32)
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Quantizer<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        for (c, out) in output.iter_mut().enumerate() {
+            for n in 0..ctx.frames() {
+                let pitch = Operand::Input(0).read::<N, C, S>(inputs, c, n).to_f32();
+                out[n] = S::from_f32(self.quantize(pitch));
+            }
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("quantizer")
+    }
+
+    /// The scale is saved as its bit mask.
+    fn params(&self) -> Params {
+        Params::new()
+            .with("scale", self.scale.0 as u32)
+            .with("root", self.root as f32)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "scale" => self.scale = Scale(value.count()? as u16),
+            "root" => self.root = value.number()?.round() as i32,
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
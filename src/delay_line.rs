@@ -1,35 +1,101 @@
-use std::collections::VecDeque;
-
+use crate::audio_context::AudioContext;
 use crate::node::Node;
 use crate::buffer::{Frame};
+use crate::param::{ParamError, Params, Value};
+use crate::sample::Sample;
+
+/// A single channel ring buffer that can be read any number of samples
+/// into the past, including between samples. Allocated once, up to a
+/// maximum delay, so it can be modulated freely on the audio thread.
+pub struct DelayBuffer<S: Sample = f32> {
+    buffer: Vec<S>,
+    write: usize,
+}
+
+impl<S: Sample> DelayBuffer<S> {
+    pub fn new(max_delay: usize) -> Self {
+        Self {
+            buffer: vec![S::ZERO; max_delay + 2],
+            write: 0,
+        }
+    }
+    /// The longest delay that can be read.
+    pub fn max_delay(&self) -> usize {
+        self.buffer.len() - 2
+    }
+    #[inline(always)]
+    pub fn push(&mut self, input: S) {
+        self.write = (self.write + 1) % self.buffer.len();
+        self.buffer[self.write] = input;
+    }
+    /// The sample pushed `delay` pushes ago, zero being the latest.
+    #[inline(always)]
+    pub fn read(&self, delay: usize) -> S {
+        let len = self.buffer.len();
+        self.buffer[(self.write + len - delay.min(len - 1)) % len]
+    }
+    /// Reads between samples with linear interpolation.
+    #[inline(always)]
+    pub fn read_fractional(&self, delay: f32) -> S {
+        let delay = delay.clamp(0.0, self.max_delay() as f32);
+        let whole = delay as usize;
+        let fraction = S::from_f32(delay - whole as f32);
+        let (a, b) = (self.read(whole), self.read(whole + 1));
+        a + (b - a) * fraction
+    }
+}
 
 /// A multichannel delay line
-pub struct DelayLine<const FRAME_SIZE: usize, const CHANNELS: usize> {
-    ringbuf: VecDeque<f32>
+pub struct DelayLine<const FRAME_SIZE: usize, const CHANNELS: usize, S: Sample = f32> {
+    buffers: Vec<DelayBuffer<S>>,
+    delay: usize,
 }
 
-impl<const N: usize, const C: usize> DelayLine<N, C>{
+impl<const N: usize, const C: usize, S: Sample> DelayLine<N, C, S>{
+    /// Delays every channel by `sample_size` samples.
     pub fn new(sample_size: usize) -> Self {
         Self {
-            ringbuf: VecDeque::with_capacity(sample_size)
+            buffers: (0..C).map(|_| DelayBuffer::new(sample_size)).collect(),
+            delay: sample_size,
         }
     }
-    #[inline(always)]
-    fn tick(&mut self, input: f32) -> f32 {
-        let sample = self.ringbuf.pop_front();
-        self.ringbuf.push_back(input);
-        sample.unwrap_or(0.0)
+    /// Changes the delay, up to the length given on construction.
+    pub fn set_delay(&mut self, sample_size: usize) {
+        self.delay = sample_size.min(self.buffers.first().map_or(0, DelayBuffer::max_delay));
     }
 }
-impl <const N: usize, const C: usize> Node<N,C> for DelayLine<N,C> {
+impl <const N: usize, const C: usize, S: Sample> Node<N, C, S> for DelayLine<N, C, S> {
     #[inline(always)]
-    fn process(&mut self, inputs: &[Frame<N, C>], output: &mut Frame<N, C>) {
-        let input = inputs[0];
-        for n in 0..N {
-            for c in 0..C {
-                let sample = self.tick(input[c][n]);
-                output[c][n] = sample
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let input = &inputs[0];
+        for ((buffer, input), output) in self.buffers.iter_mut().zip(input).zip(output.iter_mut()) {
+            for n in 0..ctx.frames() {
+                buffer.push(input[n]);
+                output[n] = buffer.read(self.delay);
+            }
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("delay")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("samples", self.delay)
+    }
+
+    /// Delays longer than the line was built for reallocate it.
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "samples" => {
+                let samples = value.count()?;
+                if samples > self.buffers.first().map_or(0, DelayBuffer::max_delay) {
+                    *self = Self::new(samples);
+                }
+                self.set_delay(samples);
             }
+            _ => return Err(ParamError::Unknown),
         }
+        Ok(())
     }
-}
\ No newline at end of file
+}
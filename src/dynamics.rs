@@ -0,0 +1,339 @@
+use std::sync::Arc;
+
+use crate::atomic::AtomicF32;
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::node::Node;
+use crate::param::{Choice, ParamError, Params, Value};
+use crate::sample::{db_to_gain, gain_to_db, Sample};
+
+/// The window used to average the signal when detecting RMS levels.
+const RMS_WINDOW_MS: f32 = 10.0;
+
+/// What a [`Dynamics`] node does to the signal once it crosses the
+/// threshold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DynamicsMode {
+    /// Turns levels above the threshold down by the ratio.
+    Compressor,
+    /// Turns levels below the threshold further down by the ratio.
+    Expander,
+    /// Turns levels below the threshold down by the range, after holding
+    /// open for the hold time.
+    Gate,
+}
+
+/// How the level of the key signal is measured.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Detection {
+    /// The absolute value of each sample, quick to catch transients.
+    #[default]
+    Peak,
+    /// The average power over a short window, closer to perceived
+    /// loudness.
+    Rms,
+}
+
+impl Choice for Detection {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("peak", Detection::Peak),
+        ("rms", Detection::Rms),
+    ];
+}
+
+#[inline(always)]
+fn coefficient(ms: f32, sample_rate: f32) -> f32 {
+    (-1.0 / (ms.max(0.001) * 0.001 * sample_rate)).exp()
+}
+
+/// A compressor, expander or noise gate. The level is taken from the
+/// second input when one is connected, which makes it a sidechain, and
+/// from the signal itself otherwise. With stereo link on, every channel
+/// follows the loudest one so the image stays put.
+///
+/// Attack is how quickly gain reduction is applied for a compressor,
+/// and how quickly an expander or gate opens back up.
+///
+/// The deepest gain reduction of each block, in decibels, is published
+/// through [`Dynamics::meter`] for a UI to read.
+pub struct Dynamics<const N: usize, const C: usize> {
+    mode: DynamicsMode,
+    detection: Detection,
+    sample_rate: f32,
+    threshold: f32,
+    ratio: f32,
+    knee: f32,
+    makeup: f32,
+    range: f32,
+    attack: f32,
+    release: f32,
+    rms: f32,
+    hold_samples: usize,
+    /// The times as given, kept for saving.
+    attack_ms: f32,
+    release_ms: f32,
+    hold_ms: f32,
+    stereo_link: bool,
+    levels: [f32; C],
+    reductions: [f32; C],
+    holds: [usize; C],
+    meter: Arc<AtomicF32>,
+}
+
+impl<const N: usize, const C: usize> Dynamics<N, C> {
+    fn new(mode: DynamicsMode, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate as f32;
+        Self {
+            mode,
+            detection: Detection::Peak,
+            sample_rate,
+            threshold: -18.0,
+            ratio: 4.0,
+            knee: 6.0,
+            makeup: 0.0,
+            range: -80.0,
+            attack: coefficient(10.0, sample_rate),
+            release: coefficient(100.0, sample_rate),
+            rms: coefficient(RMS_WINDOW_MS, sample_rate),
+            hold_samples: 0,
+            attack_ms: 10.0,
+            release_ms: 100.0,
+            hold_ms: 0.0,
+            stereo_link: true,
+            levels: [0.0; C],
+            reductions: [0.0; C],
+            holds: [0; C],
+            meter: Arc::new(AtomicF32::new(0.0)),
+        }
+    }
+
+    /// A 4:1 compressor at -18 dB with a 6 dB knee.
+    pub fn compressor(sample_rate: u32) -> Self {
+        Self::new(DynamicsMode::Compressor, sample_rate)
+    }
+
+    /// A 2:1 downward expander at -40 dB.
+    pub fn expander(sample_rate: u32) -> Self {
+        let mut expander = Self::new(DynamicsMode::Expander, sample_rate);
+        expander.set_threshold(-40.0);
+        expander.set_ratio(2.0);
+        expander.set_attack(1.0);
+        expander
+    }
+
+    /// A gate at -50 dB, closing by 80 dB after 10 ms below the threshold.
+    pub fn gate(sample_rate: u32) -> Self {
+        let mut gate = Self::new(DynamicsMode::Gate, sample_rate);
+        gate.set_threshold(-50.0);
+        gate.set_attack(0.5);
+        gate.set_release(50.0);
+        gate.set_hold(10.0);
+        gate
+    }
+
+    pub fn set_threshold(&mut self, db: f32) {
+        self.threshold = db;
+    }
+    /// For a compressor, how many decibels over the threshold give one
+    /// decibel out. For an expander, how many decibels under the threshold
+    /// each decibel under becomes.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.max(1.0);
+    }
+    /// The width in decibels of the soft knee around the threshold.
+    pub fn set_knee(&mut self, db: f32) {
+        self.knee = db.max(0.0);
+    }
+    pub fn set_makeup(&mut self, db: f32) {
+        self.makeup = db;
+    }
+    /// How far a closed gate turns the signal down, in decibels.
+    pub fn set_range(&mut self, db: f32) {
+        self.range = db.min(0.0);
+    }
+    pub fn set_attack(&mut self, ms: f32) {
+        self.attack_ms = ms;
+        self.attack = coefficient(ms, self.sample_rate);
+    }
+    pub fn set_release(&mut self, ms: f32) {
+        self.release_ms = ms;
+        self.release = coefficient(ms, self.sample_rate);
+    }
+    /// How long a gate stays open after the level falls below the
+    /// threshold.
+    pub fn set_hold(&mut self, ms: f32) {
+        self.hold_ms = ms;
+        self.hold_samples = (ms.max(0.0) * 0.001 * self.sample_rate) as usize;
+    }
+    pub fn set_detection(&mut self, detection: Detection) {
+        self.detection = detection;
+    }
+    pub fn set_stereo_link(&mut self, stereo_link: bool) {
+        self.stereo_link = stereo_link;
+    }
+
+    /// A handle to the current gain reduction in decibels, zero or below.
+    pub fn meter(&self) -> Arc<AtomicF32> {
+        Arc::clone(&self.meter)
+    }
+
+    /// The gain reduction in decibels wanted for a level in decibels.
+    #[inline(always)]
+    fn target_reduction(&self, level: f32, channel: usize) -> f32 {
+        let (threshold, knee, ratio) = (self.threshold, self.knee, self.ratio);
+        let over = level - threshold;
+        match self.mode {
+            DynamicsMode::Compressor => {
+                if 2.0 * over < -knee {
+                    0.0
+                } else if knee > 0.0 && 2.0 * over.abs() <= knee {
+                    let x = over + knee * 0.5;
+                    (1.0 / ratio - 1.0) * x * x / (2.0 * knee)
+                } else {
+                    over * (1.0 / ratio - 1.0)
+                }
+            }
+            DynamicsMode::Expander => {
+                if 2.0 * over > knee {
+                    0.0
+                } else if knee > 0.0 && 2.0 * over.abs() <= knee {
+                    let x = over - knee * 0.5;
+                    -(ratio - 1.0) * x * x / (2.0 * knee)
+                } else {
+                    (over * (ratio - 1.0)).max(self.range)
+                }
+            }
+            DynamicsMode::Gate => {
+                if over >= 0.0 || self.holds[channel] > 0 {
+                    0.0
+                } else {
+                    self.range
+                }
+            }
+        }
+    }
+
+    /// Follows the key signal for one channel, and returns the smoothed
+    /// gain reduction in decibels.
+    #[inline(always)]
+    fn tick(&mut self, channel: usize, key: f32) -> f32 {
+        let level = match self.detection {
+            Detection::Peak => key.abs(),
+            Detection::Rms => {
+                let power = &mut self.levels[channel];
+                *power = self.rms * *power + (1.0 - self.rms) * key * key;
+                power.sqrt()
+            }
+        };
+        let level_db = gain_to_db(level);
+
+        if self.mode == DynamicsMode::Gate {
+            let hold = &mut self.holds[channel];
+            *hold = if level_db >= self.threshold { self.hold_samples } else { hold.saturating_sub(1) };
+        }
+
+        let target = self.target_reduction(level_db, channel);
+        let current = &mut self.reductions[channel];
+        // Compressors attack as the reduction deepens, expanders and gates
+        // attack as they open back up.
+        let deepening = target < *current;
+        let attacking = match self.mode {
+            DynamicsMode::Compressor => deepening,
+            DynamicsMode::Expander | DynamicsMode::Gate => !deepening,
+        };
+        let coefficient = if attacking { self.attack } else { self.release };
+        *current = coefficient * *current + (1.0 - coefficient) * target;
+        *current
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Dynamics<N, C> {
+    #[inline(always)]
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let input = &inputs[0];
+        let key = inputs.get(1).unwrap_or(input);
+        let mut deepest = 0.0f32;
+
+        for n in 0..ctx.frames() {
+            if self.stereo_link {
+                let loudest = key.iter().fold(0.0f32, |loudest, channel| loudest.max(channel[n].to_f32().abs()));
+                let reduction = self.tick(0, loudest);
+                deepest = deepest.min(reduction);
+                let gain = S::from_f32(db_to_gain(reduction + self.makeup));
+                for (out, input) in output.iter_mut().zip(input) {
+                    out[n] = input[n] * gain;
+                }
+            } else {
+                for (c, out) in output.iter_mut().enumerate() {
+                    let reduction = self.tick(c, key[c][n].to_f32());
+                    deepest = deepest.min(reduction);
+                    out[n] = input[c][n] * S::from_f32(db_to_gain(reduction + self.makeup));
+                }
+            }
+        }
+
+        self.meter.store(deepest);
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some(match self.mode {
+            DynamicsMode::Compressor => "compressor",
+            DynamicsMode::Expander => "expander",
+            DynamicsMode::Gate => "gate",
+        })
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("threshold", self.threshold)
+            .with("ratio", self.ratio)
+            .with("knee", self.knee)
+            .with("makeup", self.makeup)
+            .with("range", self.range)
+            .with("attack", self.attack_ms)
+            .with("release", self.release_ms)
+            .with("hold", self.hold_ms)
+            .with("detection", Value::from_choice(self.detection))
+            .with("stereo_link", self.stereo_link)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "threshold" => self.set_threshold(value.float()?),
+            "ratio" => self.set_ratio(value.float()?),
+            "knee" => self.set_knee(value.float()?),
+            "makeup" => self.set_makeup(value.float()?),
+            "range" => self.set_range(value.float()?),
+            "attack" => self.set_attack(value.float()?),
+            "release" => self.set_release(value.float()?),
+            "hold" => self.set_hold(value.float()?),
+            "detection" => self.set_detection(value.choice()?),
+            "stereo_link" => self.set_stereo_link(value.bool()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dynamics;
+    use crate::audio_graph::DynamicAudioGraph;
+    use crate::cv::Constant;
+
+    #[test]
+    fn hard_knee_at_threshold() {
+        for mut dynamics in [Dynamics::compressor(48_000), Dynamics::expander(48_000)] {
+            dynamics.set_threshold(0.0);
+            dynamics.set_knee(0.0);
+            let mut graph: DynamicAudioGraph<64, 2> = DynamicAudioGraph::with_capacity(2);
+            let constant = graph.add_node(Box::new(Constant::new(1.0)));
+            let dynamics = graph.add_node(Box::new(dynamics));
+            graph.add_edge(constant, dynamics);
+            graph.set_sink_index(dynamics);
+            for _ in 0..4 {
+                assert!(graph.next_block(64).iter().all(|channel| channel.iter().all(|sample| sample.is_finite())));
+            }
+        }
+    }
+}
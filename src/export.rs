@@ -0,0 +1,177 @@
+use std::fmt::Write;
+
+use crate::audio_graph::DynamicAudioGraph;
+use crate::channel::ChannelMap;
+use crate::param::Value;
+use crate::patch::{write_number, Written};
+use crate::sample::Sample;
+
+/// Text dumps of a graph's topology, for visualising and diffing patches
+/// while debugging. Nodes are identified by index, along with their
+/// [`node_name`](DynamicAudioGraph::node_name) where they have one.
+impl<const N: usize, const C: usize, S: Sample> DynamicAudioGraph<N, C, S> {
+    /// The graph in Graphviz DOT, flowing left to right. Each node shows
+    /// its index, name, type and settings, edges are labelled with the
+    /// port they feed, and the sink is drawn with a double border.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph audio_graph {\n    rankdir=LR;\n    node [shape=box];\n");
+        for index in 0..self.node_count() {
+            let node = self.node(index);
+            let mut label = format!("#{index}");
+            if let Some(name) = self.node_name(index) {
+                let _ = write!(label, " {name}");
+            }
+            let _ = write!(label, "\n{}", node.type_name().unwrap_or("?"));
+            for (name, value) in node.params().iter() {
+                let _ = write!(label, "\n{name}={}", Written(value));
+            }
+            let sink = match index == self.sink_index() {
+                true => ", peripheries=2",
+                false => "",
+            };
+            let _ = writeln!(dot, "    n{index} [label={}{sink}];", dot_string(&label));
+        }
+        for to in 0..self.node_count() {
+            for (port, from) in self.incoming(to).enumerate() {
+                let mapped = match self.channel_map(from, to) {
+                    ChannelMap::Auto => "",
+                    ChannelMap::Matrix(_) => " (map)",
+                };
+                let _ = writeln!(dot, "    n{from} -> n{to} [label=\"{port}{mapped}\"];");
+            }
+        }
+        let order: Vec<String> = self.sort_order().iter().map(usize::to_string).collect();
+        let _ = writeln!(dot, "    // sort order: {}", order.join(" "));
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The graph as JSON, with the sample rate, the sink, the output
+    /// buses, the sort order, every node with its settings, and every
+    /// edge with its port and any channel map. Nodes that can't be saved
+    /// have a `null` type.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\n");
+        let _ = writeln!(json, "  \"sample_rate\": {},", self.context().get_sample_rate());
+        let _ = writeln!(json, "  \"sink\": {},", self.sink_index());
+        json.push_str("  \"buses\": [");
+        for bus in 0..self.bus_count() {
+            if bus > 0 {
+                json.push_str(", ");
+            }
+            json.push_str("{\"name\": ");
+            json_string(&mut json, self.bus_name(bus));
+            let _ = write!(json, ", \"node\": {}}}", self.bus_node(bus));
+        }
+        json.push_str("],\n");
+        let order: Vec<String> = self.sort_order().iter().map(usize::to_string).collect();
+        let _ = writeln!(json, "  \"sort_order\": [{}],", order.join(", "));
+
+        json.push_str("  \"nodes\": [");
+        for index in 0..self.node_count() {
+            let node = self.node(index);
+            json.push_str(if index == 0 { "\n" } else { ",\n" });
+            let _ = write!(json, "    {{\"index\": {index}, \"name\": ");
+            match self.node_name(index) {
+                Some(name) => json_string(&mut json, name),
+                None => json.push_str("null"),
+            }
+            json.push_str(", \"type\": ");
+            match node.type_name() {
+                Some(type_name) => json_string(&mut json, type_name),
+                None => json.push_str("null"),
+            }
+            let _ = write!(json, ", \"output_channels\": {}, \"params\": {{", self.output_channels(index));
+            for (i, (name, value)) in node.params().iter().enumerate() {
+                if i > 0 {
+                    json.push_str(", ");
+                }
+                json_string(&mut json, name);
+                json.push_str(": ");
+                json_value(&mut json, value);
+            }
+            json.push_str("}}");
+        }
+        json.push_str(if self.node_count() == 0 { "],\n" } else { "\n  ],\n" });
+
+        json.push_str("  \"edges\": [");
+        let mut first = true;
+        for to in 0..self.node_count() {
+            for (port, from) in self.incoming(to).enumerate() {
+                json.push_str(if first { "\n" } else { ",\n" });
+                first = false;
+                let _ = write!(json, "    {{\"from\": {from}, \"to\": {to}, \"port\": {port}, \"map\": ");
+                match self.channel_map(from, to) {
+                    ChannelMap::Auto => json.push_str("null"),
+                    ChannelMap::Matrix(gains) => {
+                        let rows = gains.iter().map(|row| Value::from_floats(row.iter().copied())).collect();
+                        json_value(&mut json, &Value::List(rows));
+                    }
+                }
+                json.push('}');
+            }
+        }
+        json.push_str(if first { "]\n" } else { "\n  ]\n" });
+        json.push_str("}\n");
+        json
+    }
+}
+
+/// A DOT string literal.
+fn dot_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            // Left justified line breaks keep the settings aligned.
+            '\n' => quoted.push_str("\\l"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push_str("\\l\"");
+    quoted
+}
+
+fn json_string(json: &mut String, text: &str) {
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+fn json_value(json: &mut String, value: &Value) {
+    match value {
+        // JSON has no infinities or NaN.
+        Value::Number(number) if !number.is_finite() => json.push_str("null"),
+        &Value::Number(number) => {
+            let _ = write_number(json, number);
+        }
+        Value::Bool(value) => {
+            let _ = write!(json, "{value}");
+        }
+        Value::Text(text) => json_string(json, text),
+        Value::List(values) => {
+            json.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    json.push_str(", ");
+                }
+                json_value(json, value);
+            }
+            json.push(']');
+        }
+    }
+}
@@ -0,0 +1,530 @@
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::node::Node;
+use crate::param::{ParamError, Params, Value};
+use crate::sample::Sample;
+
+#[derive(Debug, PartialEq)]
+pub enum ExprError {
+    /// A character that starts no token, at a byte offset.
+    UnexpectedCharacter(usize),
+    /// A token out of place, at a byte offset.
+    UnexpectedToken(usize),
+    UnexpectedEnd,
+    /// A name that is not an input, parameter, variable or function.
+    UnknownName(String),
+    /// A function called with the wrong number of arguments.
+    WrongArgumentCount(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'a> {
+    Number(f64),
+    Name(&'a str),
+    Symbol(&'static str),
+}
+
+/// Longer symbols come first, so `<=` isn't read as `<` then `=`.
+const SYMBOLS: [&str; 13] = ["<=", ">=", "<", ">", "+", "-", "*", "/", "%", "^", "(", ")", ","];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token<'_>)>, ExprError> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        let rest = &source[at..];
+        let byte = bytes[at];
+        if byte.is_ascii_whitespace() {
+            at += 1;
+        } else if byte.is_ascii_digit() || byte == b'.' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let number = rest[..len].parse().map_err(|_| ExprError::UnexpectedCharacter(at))?;
+            tokens.push((at, Token::Number(number)));
+            at += len;
+        } else if byte.is_ascii_alphabetic() || byte == b'_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push((at, Token::Name(&rest[..len])));
+            at += len;
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or(ExprError::UnexpectedCharacter(at))?;
+            tokens.push((at, Token::Symbol(symbol)));
+            at += symbol.len();
+        }
+    }
+    Ok(tokens)
+}
+
+type Function1 = fn(f64) -> f64;
+type Function2 = fn(f64, f64) -> f64;
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Const(f64),
+    Input(usize),
+    Param(usize),
+    Time,
+    SampleRate,
+    Beat,
+    Neg,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    Call1(Function1),
+    Call2(Function2),
+    Clamp,
+}
+
+impl Op {
+    /// How the op changes the height of the stack.
+    fn stack_change(&self) -> isize {
+        match self {
+            Op::Const(_) | Op::Input(_) | Op::Param(_) | Op::Time | Op::SampleRate | Op::Beat => 1,
+            Op::Neg | Op::Call1(_) => 0,
+            Op::Clamp => -2,
+            _ => -1,
+        }
+    }
+}
+
+const FUNCTIONS_1: [(&str, Function1); 14] = [
+    ("sin", f64::sin),
+    ("cos", f64::cos),
+    ("tan", f64::tan),
+    ("tanh", f64::tanh),
+    ("abs", f64::abs),
+    ("sqrt", f64::sqrt),
+    ("exp", f64::exp),
+    ("ln", f64::ln),
+    ("log10", f64::log10),
+    ("floor", f64::floor),
+    ("ceil", f64::ceil),
+    ("round", f64::round),
+    ("fract", f64::fract),
+    ("sign", f64::signum),
+];
+
+const FUNCTIONS_2: [(&str, Function2); 4] = [
+    ("min", f64::min),
+    ("max", f64::max),
+    ("pow", f64::powf),
+    ("atan2", f64::atan2),
+];
+
+/// A recursive descent parser, emitting ops in evaluation order.
+struct Parser<'a, 'p> {
+    tokens: Vec<(usize, Token<'a>)>,
+    position: usize,
+    params: &'p [&'p str],
+    code: Vec<Op>,
+}
+
+impl<'a> Parser<'a, '_> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.position).map(|&(_, token)| token)
+    }
+    fn next(&mut self) -> Result<(usize, Token<'a>), ExprError> {
+        let token = self.tokens.get(self.position).copied().ok_or(ExprError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+    fn eat(&mut self, symbol: &'static str) -> bool {
+        let found = self.peek() == Some(Token::Symbol(symbol));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+    fn expect(&mut self, symbol: &str) -> Result<(), ExprError> {
+        match self.next()? {
+            (_, Token::Symbol(found)) if found == symbol => Ok(()),
+            (at, _) => Err(ExprError::UnexpectedToken(at)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<(), ExprError> {
+        self.additive()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("<")) => Op::Less,
+            Some(Token::Symbol(">")) => Op::Greater,
+            Some(Token::Symbol("<=")) => Op::LessEqual,
+            Some(Token::Symbol(">=")) => Op::GreaterEqual,
+            _ => return Ok(()),
+        };
+        self.position += 1;
+        self.additive()?;
+        self.code.push(op);
+        Ok(())
+    }
+    fn additive(&mut self) -> Result<(), ExprError> {
+        self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("+")) => Op::Add,
+                Some(Token::Symbol("-")) => Op::Sub,
+                _ => return Ok(()),
+            };
+            self.position += 1;
+            self.term()?;
+            self.code.push(op);
+        }
+    }
+    fn term(&mut self) -> Result<(), ExprError> {
+        self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("*")) => Op::Mul,
+                Some(Token::Symbol("/")) => Op::Div,
+                Some(Token::Symbol("%")) => Op::Rem,
+                _ => return Ok(()),
+            };
+            self.position += 1;
+            self.unary()?;
+            self.code.push(op);
+        }
+    }
+    fn unary(&mut self) -> Result<(), ExprError> {
+        if self.eat("-") {
+            self.unary()?;
+            self.code.push(Op::Neg);
+            return Ok(());
+        }
+        self.primary()?;
+        // Powers bind right to left, and tighter than a leading minus.
+        if self.eat("^") {
+            self.unary()?;
+            self.code.push(Op::Pow);
+        }
+        Ok(())
+    }
+    fn primary(&mut self) -> Result<(), ExprError> {
+        match self.next()? {
+            (_, Token::Number(value)) => self.code.push(Op::Const(value)),
+            (_, Token::Symbol("(")) => {
+                self.expression()?;
+                self.expect(")")?;
+            }
+            (_, Token::Name(name)) if self.peek() == Some(Token::Symbol("(")) => {
+                self.position += 1;
+                let mut arguments = 0;
+                if !self.eat(")") {
+                    loop {
+                        self.expression()?;
+                        arguments += 1;
+                        if !self.eat(",") {
+                            break;
+                        }
+                    }
+                    self.expect(")")?;
+                }
+                self.code.push(function(name, arguments)?);
+            }
+            (_, Token::Name(name)) => self.code.push(self.variable(name)?),
+            (at, _) => return Err(ExprError::UnexpectedToken(at)),
+        }
+        Ok(())
+    }
+    fn variable(&self, name: &str) -> Result<Op, ExprError> {
+        if let Some(index) = self.params.iter().position(|&param| param == name) {
+            return Ok(Op::Param(index));
+        }
+        Ok(match name {
+            "t" => Op::Time,
+            "sr" => Op::SampleRate,
+            "beat" => Op::Beat,
+            "pi" => Op::Const(core::f64::consts::PI),
+            _ => match name.strip_prefix("in").and_then(|port| port.parse().ok()) {
+                Some(port) => Op::Input(port),
+                None => return Err(ExprError::UnknownName(name.to_string())),
+            },
+        })
+    }
+}
+
+fn function(name: &str, arguments: usize) -> Result<Op, ExprError> {
+    let known = |found: bool| match found {
+        true => ExprError::WrongArgumentCount(name.to_string()),
+        false => ExprError::UnknownName(name.to_string()),
+    };
+    if let Some(&(_, f)) = FUNCTIONS_1.iter().find(|(known, _)| *known == name) {
+        return if arguments == 1 { Ok(Op::Call1(f)) } else { Err(known(true)) };
+    }
+    if let Some(&(_, f)) = FUNCTIONS_2.iter().find(|(known, _)| *known == name) {
+        return if arguments == 2 { Ok(Op::Call2(f)) } else { Err(known(true)) };
+    }
+    match name {
+        "clamp" if arguments == 3 => Ok(Op::Clamp),
+        "clamp" => Err(known(true)),
+        _ => Err(known(false)),
+    }
+}
+
+/// Computes its output from a formula, such as
+/// `sin(in0 * 2) * 0.5 + in1`, so DSP can be tried out without
+/// recompiling. Each channel is evaluated separately.
+///
+/// Formulas can use:
+/// * `in0`, `in1`, ... the same channel of the input on each port.
+/// * `t`, seconds since the node started, `sr`, the sample rate, and
+///   `beat`, the transport's beat position.
+/// * named parameters, set while running with [`Expr::set_param`].
+/// * `pi`, `+ - * / % ^`, comparisons giving `1` or `0`, and the
+///   functions `sin cos tan tanh abs sqrt exp ln log10 floor ceil round
+///   fract sign`, `min max pow atan2` and `clamp(x, low, high)`.
+///
+/// The formula is compiled to bytecode for a stack machine on
+/// construction, and evaluated without allocating.
+pub struct Expr<const N: usize> {
+    source: String,
+    param_names: Vec<String>,
+    params: Vec<f64>,
+    code: Vec<Op>,
+    stack: Vec<f64>,
+    elapsed: u64,
+}
+
+impl<const N: usize> Expr<N> {
+    /// Compiles `source`, which may refer to `params` by name. Every
+    /// parameter starts at zero.
+    pub fn new(source: &str, params: &[&str]) -> Result<Self, ExprError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+            params,
+            code: Vec::new(),
+        };
+        parser.expression()?;
+        if let Some(&(at, _)) = parser.tokens.get(parser.position) {
+            return Err(ExprError::UnexpectedToken(at));
+        }
+
+        let mut depth: isize = 0;
+        let mut max_depth = 0;
+        for op in &parser.code {
+            depth += op.stack_change();
+            max_depth = max_depth.max(depth);
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            param_names: params.iter().map(|param| param.to_string()).collect(),
+            params: vec![0.0; params.len()],
+            stack: vec![0.0; max_depth as usize],
+            code: parser.code,
+            elapsed: 0,
+        })
+    }
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    pub fn param_names(&self) -> &[String] {
+        &self.param_names
+    }
+    /// Sets a named parameter, returning whether the formula has it.
+    pub fn set_param(&mut self, name: &str, value: f32) -> bool {
+        match self.param_names.iter().position(|param| param == name) {
+            Some(index) => {
+                self.params[index] = value as f64;
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn param(&self, name: &str) -> Option<f32> {
+        let index = self.param_names.iter().position(|param| param == name)?;
+        Some(self.params[index] as f32)
+    }
+
+    /// Recompiles with a new formula or parameter names, keeping the
+    /// values of parameters that are still named.
+    fn recompile(&mut self, source: &str, names: &[&str]) -> Result<(), ParamError> {
+        let mut expr = Self::new(source, names).map_err(|_| ParamError::Invalid)?;
+        for (name, &value) in self.param_names.iter().zip(&self.params) {
+            expr.set_param(name, value as f32);
+        }
+        expr.elapsed = self.elapsed;
+        *self = expr;
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn evaluate<const C: usize, S: Sample>(
+        &mut self,
+        ctx: &AudioContext,
+        inputs: &[Frame<N, C, S>],
+        channel: usize,
+        n: usize,
+    ) -> f64 {
+        let sample_rate = *ctx.get_sample_rate() as f64;
+        let stack = &mut self.stack;
+        let mut top = 0;
+        for op in &self.code {
+            match *op {
+                Op::Const(value) => {
+                    stack[top] = value;
+                    top += 1;
+                }
+                Op::Input(port) => {
+                    stack[top] = inputs.get(port).map_or(0.0, |input| input[channel][n].to_f64());
+                    top += 1;
+                }
+                Op::Param(index) => {
+                    stack[top] = self.params[index];
+                    top += 1;
+                }
+                Op::Time => {
+                    stack[top] = (self.elapsed + n as u64) as f64 / sample_rate;
+                    top += 1;
+                }
+                Op::SampleRate => {
+                    stack[top] = sample_rate;
+                    top += 1;
+                }
+                Op::Beat => {
+                    stack[top] = ctx.transport().beat_at(n);
+                    top += 1;
+                }
+                Op::Neg => stack[top - 1] = -stack[top - 1],
+                Op::Call1(f) => stack[top - 1] = f(stack[top - 1]),
+                Op::Clamp => {
+                    top -= 2;
+                    stack[top - 1] = stack[top - 1].max(stack[top]).min(stack[top + 1]);
+                }
+                op => {
+                    top -= 1;
+                    let (a, b) = (stack[top - 1], stack[top]);
+                    stack[top - 1] = match op {
+                        Op::Add => a + b,
+                        Op::Sub => a - b,
+                        Op::Mul => a * b,
+                        Op::Div => a / b,
+                        Op::Rem => a % b,
+                        Op::Pow => a.powf(b),
+                        Op::Less => (a < b) as u8 as f64,
+                        Op::Greater => (a > b) as u8 as f64,
+                        Op::LessEqual => (a <= b) as u8 as f64,
+                        Op::GreaterEqual => (a >= b) as u8 as f64,
+                        Op::Call2(f) => f(a, b),
+                        _ => unreachable!(),
+                    };
+                }
+            }
+        }
+        stack[0]
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Expr<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        for (c, out) in output.iter_mut().enumerate() {
+            for (n, sample) in out[..ctx.frames()].iter_mut().enumerate() {
+                *sample = S::from_f64(self.evaluate(ctx, inputs, c, n));
+            }
+        }
+        self.elapsed += ctx.frames() as u64;
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("expr")
+    }
+
+    /// The parameter names come before the formula using them, and each
+    /// parameter's value is saved under its own name.
+    fn params(&self) -> Params {
+        let names = self.param_names.iter().map(|name| Value::from(name.as_str()));
+        let mut params = Params::new()
+            .with("params", Value::List(names.collect()))
+            .with("source", self.source.as_str());
+        for (name, &value) in self.param_names.iter().zip(&self.params) {
+            params.push(name, value);
+        }
+        params
+    }
+
+    /// Changing the formula or the parameter names recompiles it.
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "source" => {
+                let names = self.param_names.clone();
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                self.recompile(value.text()?, &names)
+            }
+            "params" => {
+                let names = value.list()?.iter().map(Value::text).collect::<Result<Vec<_>, _>>()?;
+                let source = self.source.clone();
+                self.recompile(&source, &names)
+            }
+            _ if self.param(name).is_some() => {
+                Expr::set_param(self, name, value.float()?);
+                Ok(())
+            }
+            _ => Err(ParamError::Unknown),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expr, ExprError};
+    use crate::audio_context::AudioContext;
+    use crate::buffer::Frame;
+
+    fn eval(source: &str) -> f64 {
+        let mut expr = Expr::<1>::new(source, &["x"]).unwrap();
+        expr.set_param("x", 3.0);
+        let inputs: [Frame<1, 1>; 0] = [];
+        expr.evaluate(&AudioContext::new(48_000), &inputs, 0, 0)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("10 - 4 - 3"), 3.0);
+        assert_eq!(eval("x * 2 < 7"), 1.0);
+        assert_eq!(eval("1 + 1 >= 3"), 0.0);
+        assert_eq!(eval("-x * 2"), -6.0);
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(eval("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(eval("-2 ^ 2"), -4.0);
+        assert_eq!(eval("2 ^ -1"), 0.5);
+        assert_eq!(eval("2 * 3 ^ 2"), 18.0);
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval("max(x, 5)"), 5.0);
+        assert_eq!(eval("clamp(x, 0, 1)"), 1.0);
+        assert_eq!(eval("abs(-x) + sr"), 48_003.0);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source: &str| Expr::<1>::new(source, &[]).err();
+        assert_eq!(error("1 + $"), Some(ExprError::UnexpectedCharacter(4)));
+        assert_eq!(error("1 + * 2"), Some(ExprError::UnexpectedToken(4)));
+        assert_eq!(error("(1 + 2"), Some(ExprError::UnexpectedEnd));
+        assert_eq!(error("1 2"), Some(ExprError::UnexpectedToken(2)));
+        assert_eq!(error("y + 1"), Some(ExprError::UnknownName("y".to_string())));
+        assert_eq!(error("wobble(1)"), Some(ExprError::UnknownName("wobble".to_string())));
+        assert_eq!(error("sin(1, 2)"), Some(ExprError::WrongArgumentCount("sin".to_string())));
+        assert_eq!(error("max(1)"), Some(ExprError::WrongArgumentCount("max".to_string())));
+        assert_eq!(error("clamp()"), Some(ExprError::WrongArgumentCount("clamp".to_string())));
+    }
+}
@@ -0,0 +1,125 @@
+use core::ops::{Add, Mul, Sub};
+
+use crate::sample::Sample;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex<S: Sample> {
+    pub re: S,
+    pub im: S,
+}
+
+impl<S: Sample> Complex<S> {
+    pub const ZERO: Self = Complex { re: S::ZERO, im: S::ZERO };
+
+    #[inline(always)]
+    pub fn new(re: S, im: S) -> Self {
+        Self { re, im }
+    }
+}
+
+impl<S: Sample> Add for Complex<S> {
+    type Output = Self;
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl<S: Sample> Sub for Complex<S> {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl<S: Sample> Mul for Complex<S> {
+    type Output = Self;
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+/// An in place radix-2 FFT of a fixed power of two size. Twiddles and
+/// the bit reversal permutation are computed up front, so transforms
+/// never allocate.
+pub struct Fft<S: Sample> {
+    twiddles: Vec<Complex<S>>,
+    bit_reverse: Vec<usize>,
+}
+
+impl<S: Sample> Fft<S> {
+    /// # Panics
+    /// If `size` is not a power of two.
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "FFT size must be a power of two");
+        let bits = size.trailing_zeros();
+        let twiddles = (0..size / 2)
+            .map(|k| {
+                let angle = -2.0 * core::f64::consts::PI * k as f64 / size as f64;
+                Complex::new(S::from_f64(angle.cos()), S::from_f64(angle.sin()))
+            })
+            .collect();
+        let bit_reverse = (0..size)
+            .map(|i| match bits {
+                0 => 0,
+                _ => i.reverse_bits() >> (usize::BITS - bits),
+            })
+            .collect();
+        Self { twiddles, bit_reverse }
+    }
+
+    pub fn size(&self) -> usize {
+        self.bit_reverse.len()
+    }
+
+    /// Transforms `buffer` from the time domain to the frequency domain.
+    pub fn forward(&self, buffer: &mut [Complex<S>]) {
+        self.transform(buffer, false);
+    }
+
+    /// Transforms `buffer` back to the time domain, including the
+    /// `1 / size` scaling.
+    pub fn inverse(&self, buffer: &mut [Complex<S>]) {
+        self.transform(buffer, true);
+        let scale = S::ONE / S::from_f64(self.size() as f64);
+        for value in buffer.iter_mut() {
+            value.re *= scale;
+            value.im *= scale;
+        }
+    }
+
+    fn transform(&self, buffer: &mut [Complex<S>], inverse: bool) {
+        let size = self.size();
+        assert_eq!(buffer.len(), size, "buffer length must match the FFT size");
+
+        for i in 0..size {
+            let j = self.bit_reverse[i];
+            if i < j {
+                buffer.swap(i, j);
+            }
+        }
+
+        let mut half = 1;
+        while half < size {
+            let stride = size / (half * 2);
+            for start in (0..size).step_by(half * 2) {
+                for k in 0..half {
+                    let mut twiddle = self.twiddles[k * stride];
+                    if inverse {
+                        twiddle.im = -twiddle.im;
+                    }
+                    let even = buffer[start + k];
+                    let odd = buffer[start + k + half] * twiddle;
+                    buffer[start + k] = even + odd;
+                    buffer[start + k + half] = even - odd;
+                }
+            }
+            half *= 2;
+        }
+    }
+}
@@ -1,5 +1,9 @@
+use crate::audio_context::AudioContext;
 use crate::node::Node;
 use crate::buffer::Frame;
+use crate::param::{ParamError, Params, Value};
+use crate::sample::Sample;
+use crate::simd;
 
 pub struct Gain<const FRAME_SIZE: usize> {
     gain: f32 // Arc<AtomicF32> might be more helpful. If you need an atomic f32 there is an easy trick
@@ -11,15 +15,32 @@ impl<const N: usize> Gain<N> {
         }
     }
 }
-impl <const N: usize, const C: usize> Node<N, C> for Gain<N> {
+impl <const N: usize, const C: usize, S: Sample> Node<N, C, S> for Gain<N> {
     #[inline(always)]
-    fn process(&mut self, inputs: &[Frame<N, C>], output: &mut Frame<N, C>){
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>){
         // This node only takes an input of one stereo buffer.
-        let input = inputs[0];
-        for n in 0..N { // For ever sample in our frame size
-            for c in 0..C { // For ever channel in our frame
-                output[c][n] = (input[c][n] * self.gain).clamp(-1.0 , 1.0);
-            }
+        let input = &inputs[0];
+        let frames = ctx.frames();
+        for (out, input) in output.iter_mut().zip(input) { // For every channel in our frame
+            let out = &mut out[..frames];
+            simd::copy(out, input);
+            simd::scale(out, S::from_f32(self.gain));
         }
     }
-}
\ No newline at end of file
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("gain")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("gain", self.gain)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "gain" => self.gain = value.float()?,
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
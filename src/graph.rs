@@ -68,7 +68,7 @@ impl<N> Graph<N> for DynamicGraph<N> {
         id
     }
     fn add_edge(&mut self, from: usize, to: usize) {
-        if (from == to) { return };
+        if from == to { return };
         self.outgoing[from].insert(to);
         self.incoming[to].insert(from);
     }
@@ -120,6 +120,47 @@ impl<N> Graph<N> for DynamicGraph<N> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<N> DynamicGraph<N> {
+    /// Groups a topological order into dependency levels. Every node in a
+    /// level only depends on nodes in earlier levels, so the nodes of a
+    /// single level can be computed in any order, or at the same time.
+    ///
+    /// Returns the reordered nodes along with the bounds of each level,
+    /// where level `l` spans `order[bounds[l]..bounds[l + 1]]`.
+    pub fn dependency_levels(&self, sort_order: &[usize]) -> (Vec<usize>, Vec<usize>) {
+        let mut depth = vec![0; self.nodes.len()];
+        let mut level_count = 0;
+        for &node_index in sort_order {
+            let level = self.incoming[node_index]
+                .iter()
+                .map(|&src| depth[src] + 1)
+                .max()
+                .unwrap_or(0);
+            depth[node_index] = level;
+            level_count = level_count.max(level + 1);
+        }
+
+        let mut bounds = vec![0; level_count + 1];
+        for &node_index in sort_order {
+            bounds[depth[node_index] + 1] += 1;
+        }
+        for level in 0..level_count {
+            bounds[level + 1] += bounds[level];
+        }
+
+        let mut cursor = bounds.clone();
+        let mut order = vec![0; sort_order.len()];
+        for &node_index in sort_order {
+            let level = depth[node_index];
+            order[cursor[level]] = node_index;
+            cursor[level] += 1;
+        }
+
+        (order, bounds)
+    }
+}
+
 // TODO: Fixed Size Graph for embedded environments
 
 // pub struct FixedGraph<const C: usize, N> {
@@ -0,0 +1,321 @@
+use core::f32::consts::{PI, TAU};
+
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::node::Node;
+use crate::noise::Rng;
+use crate::param::{Choice, ParamError, Params, Value};
+use crate::sample::Sample;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LfoShape {
+    #[default]
+    Sine,
+    Triangle,
+    /// Rises across the cycle.
+    Saw,
+    Square,
+    /// Jumps to a new random level every cycle.
+    SampleAndHold,
+    /// Glides from one random level to the next over each cycle.
+    SmoothRandom,
+}
+
+impl Choice for LfoShape {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("sine", LfoShape::Sine),
+        ("triangle", LfoShape::Triangle),
+        ("saw", LfoShape::Saw),
+        ("square", LfoShape::Square),
+        ("sample_and_hold", LfoShape::SampleAndHold),
+        ("smooth_random", LfoShape::SmoothRandom),
+    ];
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Polarity {
+    /// From `-1.0` to `1.0`.
+    #[default]
+    Bipolar,
+    /// From `0.0` to `1.0`.
+    Unipolar,
+}
+
+impl Choice for Polarity {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("bipolar", Polarity::Bipolar),
+        ("unipolar", Polarity::Unipolar),
+    ];
+}
+
+/// How fast an [`Lfo`] cycles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LfoRate {
+    Hz(f32),
+    /// The length of a cycle in beats of the graph's transport, so `0.25`
+    /// is a sixteenth note in 4/4 and `4.0` a bar. While the transport
+    /// plays, the cycle is locked to its beat position.
+    Beats(f32),
+}
+
+impl Default for LfoRate {
+    fn default() -> Self {
+        LfoRate::Hz(1.0)
+    }
+}
+
+/// How often an [`Lfo`] computes a new value. Between updates the last
+/// value is held, trading smoothness for CPU time.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Resolution {
+    /// Every sample.
+    #[default]
+    Audio,
+    /// Once at the start of every block.
+    Block,
+    /// Every given number of samples.
+    Every(usize),
+}
+
+/// A low frequency oscillator for modulating other nodes. Unlike
+/// [`Oscillator`](crate::osc::Oscillator) it can be synced to a tempo,
+/// restarted, made unipolar and run at control rate.
+///
+/// Its phase restarts whenever the first channel of its first input
+/// rises above zero, so a gate or clock connected to it retriggers the
+/// cycle, unless it is locked to a playing transport. With nothing
+/// connected it runs freely. The output is mono.
+pub struct Lfo<const N: usize> {
+    shape: LfoShape,
+    rate: LfoRate,
+    polarity: Polarity,
+    resolution: Resolution,
+    tempo: Option<f32>,
+    amplitude: f32,
+    phase: f32,
+    start_phase: f32,
+    seed: u32,
+    rng: Rng,
+    /// The random level of this cycle and the one before it.
+    random: (f32, f32),
+    value: f32,
+    countdown: usize,
+    trigger: f32,
+}
+
+impl<const N: usize> Lfo<N> {
+    pub fn new(shape: LfoShape, rate: LfoRate) -> Self {
+        let mut rng = Rng::default();
+        let random = (rng.next_bipolar(), rng.next_bipolar());
+        Self {
+            shape,
+            rate,
+            polarity: Polarity::Bipolar,
+            resolution: Resolution::Audio,
+            tempo: None,
+            amplitude: 1.0,
+            phase: 0.0,
+            start_phase: 0.0,
+            seed: 0,
+            rng,
+            random,
+            value: 0.0,
+            countdown: 0,
+            trigger: 0.0,
+        }
+    }
+    pub fn set_shape(&mut self, shape: LfoShape) {
+        self.shape = shape;
+    }
+    pub fn set_rate(&mut self, rate: LfoRate) {
+        self.rate = rate;
+    }
+    pub fn set_polarity(&mut self, polarity: Polarity) {
+        self.polarity = polarity;
+    }
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        self.resolution = resolution;
+        self.countdown = 0;
+    }
+    /// The tempo synced rates are measured against while the graph's
+    /// transport is stopped, in beats per minute. Until this is set they
+    /// follow the transport's tempo whether it plays or not.
+    pub fn set_tempo(&mut self, bpm: f32) {
+        self.tempo = Some(bpm.max(0.0));
+    }
+    pub fn set_amplitude(&mut self, amplitude: f32) {
+        self.amplitude = amplitude;
+    }
+    /// Where in the cycle, from `0.0` to `1.0`, the LFO restarts.
+    pub fn set_start_phase(&mut self, phase: f32) {
+        self.start_phase = phase.rem_euclid(1.0);
+    }
+    /// Seeds the random shapes, so renders using them are reproducible.
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+        self.rng = Rng::new(seed.into());
+        self.random = (self.rng.next_bipolar(), self.rng.next_bipolar());
+    }
+    /// Restarts the cycle from the start phase.
+    pub fn reset(&mut self) {
+        self.phase = self.start_phase;
+        self.countdown = 0;
+    }
+    /// The rate in Hz at the tempo given to [`set_tempo`](Self::set_tempo),
+    /// or 120 beats per minute if there is none.
+    pub fn frequency(&self) -> f32 {
+        self.frequency_at(self.tempo.unwrap_or(120.0))
+    }
+    #[inline(always)]
+    fn frequency_at(&self, tempo: f32) -> f32 {
+        match self.rate {
+            LfoRate::Hz(hz) => hz,
+            LfoRate::Beats(beats) => tempo / 60.0 / beats.max(f32::EPSILON),
+        }
+    }
+    /// The bipolar value of the shape at the current phase.
+    #[inline(always)]
+    fn shape_value(&self) -> f32 {
+        let phase = self.phase;
+        match self.shape {
+            LfoShape::Sine => (phase * TAU).sin(),
+            LfoShape::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            LfoShape::Saw => phase * 2.0 - 1.0,
+            LfoShape::Square => match phase < 0.5 {
+                true => 1.0,
+                false => -1.0,
+            },
+            LfoShape::SampleAndHold => self.random.1,
+            LfoShape::SmoothRandom => {
+                let (from, to) = self.random;
+                let blend = 0.5 - 0.5 * (phase * PI).cos();
+                from + (to - from) * blend
+            }
+        }
+    }
+    /// Moves to `phase`, which may be past the end of the cycle, picking
+    /// a new random level whenever the cycle wraps.
+    #[inline(always)]
+    fn move_to(&mut self, phase: f32) {
+        let wrapped = phase - phase.floor();
+        if phase >= 1.0 || wrapped < self.phase {
+            self.random = (self.random.1, self.rng.next_bipolar());
+        }
+        self.phase = wrapped;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Lfo<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let frames = ctx.frames();
+        let transport = ctx.transport();
+        let tempo = match transport.is_playing() {
+            true => transport.tempo(),
+            false => self.tempo.unwrap_or(transport.tempo()),
+        };
+        let increment = self.frequency_at(tempo) / ctx.get_sample_rate();
+        let locked = match self.rate {
+            LfoRate::Beats(beats) if transport.is_playing() => Some(beats.max(f32::EPSILON) as f64),
+            _ => None,
+        };
+        let interval = match self.resolution {
+            Resolution::Audio => 1,
+            Resolution::Block => {
+                self.countdown = 0;
+                frames
+            }
+            Resolution::Every(samples) => samples.max(1),
+        };
+        let (scale, offset) = match self.polarity {
+            Polarity::Bipolar => (self.amplitude, 0.0),
+            Polarity::Unipolar => (self.amplitude * 0.5, self.amplitude * 0.5),
+        };
+        let trigger = inputs.first().map(|input| &input[0]);
+        let Some(out) = output.first_mut() else { return };
+
+        for n in 0..frames {
+            if let Some(beats) = locked {
+                let cycles = transport.beat_at(n) / beats + self.start_phase as f64;
+                self.move_to(cycles.fract() as f32);
+            }
+            if let Some(trigger) = trigger {
+                let level = trigger[n].to_f32();
+                if level > 0.0 && self.trigger <= 0.0 {
+                    self.reset();
+                }
+                self.trigger = level;
+            }
+            if self.countdown == 0 {
+                self.value = self.shape_value() * scale + offset;
+                self.countdown = interval;
+            }
+            self.countdown -= 1;
+            out[n] = S::from_f32(self.value);
+            if locked.is_none() {
+                self.move_to(self.phase + increment);
+            }
+        }
+    }
+
+    fn input_channels(&self, _port: usize) -> usize {
+        1
+    }
+
+    fn output_channels(&self) -> usize {
+        1
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("lfo")
+    }
+
+    /// The rate is saved as either `hz` or `beats`, and the resolution as
+    /// `audio`, `block` or a number of samples.
+    fn params(&self) -> Params {
+        let mut params = Params::new().with("shape", Value::from_choice(self.shape));
+        match self.rate {
+            LfoRate::Hz(hz) => params.push("hz", hz),
+            LfoRate::Beats(beats) => params.push("beats", beats),
+        }
+        let resolution = match self.resolution {
+            Resolution::Audio => Value::from("audio"),
+            Resolution::Block => Value::from("block"),
+            Resolution::Every(samples) => Value::from(samples),
+        };
+        params.push("polarity", Value::from_choice(self.polarity));
+        params.push("resolution", resolution);
+        if let Some(tempo) = self.tempo {
+            params.push("tempo", tempo);
+        }
+        params
+            .with("amplitude", self.amplitude)
+            .with("start_phase", self.start_phase)
+            .with("seed", self.seed)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "shape" => self.set_shape(value.choice()?),
+            "hz" => self.set_rate(LfoRate::Hz(value.float()?)),
+            "beats" => self.set_rate(LfoRate::Beats(value.float()?)),
+            "polarity" => self.set_polarity(value.choice()?),
+            "resolution" => self.set_resolution(match value {
+                Value::Number(_) => Resolution::Every(value.count()?),
+                _ => match value.text()? {
+                    "audio" => Resolution::Audio,
+                    "block" => Resolution::Block,
+                    _ => return Err(ParamError::Invalid),
+                },
+            }),
+            "tempo" => self.set_tempo(value.float()?),
+            "amplitude" => self.set_amplitude(value.float()?),
+            "start_phase" => {
+                self.set_start_phase(value.float()?);
+                self.reset();
+            }
+            "seed" => self.set_seed(u32::try_from(value.count()?).map_err(|_| ParamError::Invalid)?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
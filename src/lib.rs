@@ -7,4 +7,30 @@ pub mod audio_graph;
 pub mod gain;
 pub mod delay_line;
 pub mod audio_context;
-pub mod graph;
\ No newline at end of file
+pub mod graph;
+pub mod parallel;
+pub mod simd;
+pub mod sample;
+pub mod channel;
+pub mod limiter;
+pub mod atomic;
+pub mod dynamics;
+pub mod reverb;
+pub mod fft;
+pub mod wav;
+pub mod convolver;
+pub mod modulation;
+pub mod waveshaper;
+pub mod stereo;
+pub mod routing;
+pub mod noise;
+pub mod lfo;
+pub mod transport;
+pub mod sequencer;
+pub mod cv;
+pub mod expr;
+pub mod param;
+pub mod registry;
+pub mod patch;
+pub mod export;
+pub mod subgraph;
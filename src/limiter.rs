@@ -0,0 +1,233 @@
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::node::Node;
+use crate::param::{Choice, ParamError, Params, Value};
+use crate::sample::{db_to_gain, Sample};
+
+/// A look-ahead brickwall limiter, meant to sit at the sink so the
+/// rest of the graph can use all the headroom floats give it. The input
+/// is delayed by the look-ahead, which lets the gain start falling before
+/// a peak arrives instead of clipping it. All channels share one gain so
+/// the stereo image doesn't shift.
+///
+/// Every buffer is allocated on construction.
+pub struct Limiter<const N: usize, const C: usize, S: Sample = f32> {
+    /// The settings as given, kept for saving and rebuilding.
+    sample_rate: u32,
+    lookahead_ms: f32,
+    release_ms: f32,
+    ceiling_db: f32,
+    ceiling: f32,
+    release: f32,
+    /// The delayed input, one sample of every channel per slot.
+    delay: Vec<[S; C]>,
+    /// The gains being averaged, so the gain moves smoothly into a peak.
+    smoothing: Vec<f32>,
+    smoothing_sum: f64,
+    /// A sliding minimum of the gain each sample needs, holding the
+    /// lowest gain for the whole look-ahead window.
+    hold: Vec<(usize, f32)>,
+    hold_head: usize,
+    hold_len: usize,
+    gain: f32,
+    position: usize,
+}
+
+impl<const N: usize, const C: usize, S: Sample> Limiter<N, C, S> {
+    /// `ceiling_db` is the level the output never exceeds, and
+    /// `release_ms` how long the gain takes to recover after a peak.
+    pub fn new(sample_rate: u32, lookahead_ms: f32, release_ms: f32, ceiling_db: f32) -> Self {
+        let rate = sample_rate as f32;
+        let lookahead = ((lookahead_ms * 0.001 * rate) as usize).max(1);
+        Self {
+            sample_rate,
+            lookahead_ms,
+            release_ms,
+            ceiling_db,
+            ceiling: db_to_gain(ceiling_db),
+            release: 1.0 - (-1.0 / (release_ms.max(0.01) * 0.001 * rate)).exp(),
+            delay: vec![[S::ZERO; C]; lookahead],
+            smoothing: vec![1.0; lookahead],
+            smoothing_sum: lookahead as f64,
+            hold: vec![(0, 1.0); lookahead],
+            hold_head: 0,
+            hold_len: 0,
+            gain: 1.0,
+            position: 0,
+        }
+    }
+
+    /// The delay added to the signal, in samples.
+    pub fn latency(&self) -> usize {
+        self.delay.len() - 1
+    }
+
+    /// The gain currently being applied, `1.0` when not limiting.
+    pub fn current_gain(&self) -> f32 {
+        self.smoothing_sum as f32 / self.smoothing.len() as f32
+    }
+
+    /// Pushes `target` into the sliding window and returns the smallest
+    /// target of the last look-ahead samples.
+    #[inline(always)]
+    fn hold(&mut self, index: usize, target: f32) -> f32 {
+        let window = self.hold.len();
+        // Drop values that can never be the minimum again.
+        while self.hold_len > 0 {
+            let back = (self.hold_head + self.hold_len - 1) % window;
+            if self.hold[back].1 < target {
+                break;
+            }
+            self.hold_len -= 1;
+        }
+        // Drop the oldest value once it leaves the window.
+        if self.hold_len > 0 && index - self.hold[self.hold_head].0 >= window {
+            self.hold_head = (self.hold_head + 1) % window;
+            self.hold_len -= 1;
+        }
+        let back = (self.hold_head + self.hold_len) % window;
+        self.hold[back] = (index, target);
+        self.hold_len += 1;
+        self.hold[self.hold_head].1
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Limiter<N, C, S> {
+    #[inline(always)]
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let input = &inputs[0];
+        let window = self.delay.len();
+        let ceiling = S::from_f32(self.ceiling);
+
+        for n in 0..ctx.frames() {
+            let mut peak = 0.0f32;
+            for channel in input {
+                peak = peak.max(channel[n].to_f32().abs());
+            }
+            let target = if peak > self.ceiling { self.ceiling / peak } else { 1.0 };
+
+            // Attack instantly to the held minimum, and release slowly.
+            let held = self.hold(self.position, target);
+            self.gain = if held < self.gain { held } else { self.gain + (held - self.gain) * self.release };
+
+            let slot = self.position % window;
+            self.smoothing_sum += (self.gain - self.smoothing[slot]) as f64;
+            self.smoothing[slot] = self.gain;
+            let gain = S::from_f64(self.smoothing_sum / window as f64);
+
+            // The oldest slot is the one being overwritten, `window - 1`
+            // samples behind the newest.
+            let oldest = (self.position + 1) % window;
+            for (c, out) in output.iter_mut().enumerate() {
+                self.delay[slot][c] = input[c][n];
+                out[n] = (self.delay[oldest][c] * gain).clamp(-ceiling, ceiling);
+            }
+            self.position += 1;
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("limiter")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("lookahead", self.lookahead_ms)
+            .with("release", self.release_ms)
+            .with("ceiling", self.ceiling_db)
+    }
+
+    /// Every change rebuilds the limiter, clearing its look-ahead.
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        let (mut lookahead, mut release, mut ceiling) = (self.lookahead_ms, self.release_ms, self.ceiling_db);
+        match name {
+            "lookahead" => lookahead = value.float()?,
+            "release" => release = value.float()?,
+            "ceiling" => ceiling = value.float()?,
+            _ => return Err(ParamError::Unknown),
+        }
+        *self = Self::new(self.sample_rate, lookahead, release, ceiling);
+        Ok(())
+    }
+}
+
+/// The transfer curve of a [`SoftClip`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ClipCurve {
+    /// Smoothly saturates, never quite reaching full scale.
+    #[default]
+    Tanh,
+    /// A cubic that reaches full scale at `±1.0` and stays there.
+    Cubic,
+    /// Clamps to `±1.0`.
+    Hard,
+}
+
+impl Choice for ClipCurve {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("tanh", ClipCurve::Tanh),
+        ("cubic", ClipCurve::Cubic),
+        ("hard", ClipCurve::Hard),
+    ];
+}
+
+impl ClipCurve {
+    #[inline(always)]
+    pub fn apply<S: Sample>(&self, x: S) -> S {
+        match self {
+            ClipCurve::Tanh => x.tanh(),
+            ClipCurve::Cubic => {
+                let x = x.clamp(-S::ONE, S::ONE);
+                S::from_f32(1.5) * (x - x * x * x / S::from_f32(3.0))
+            }
+            ClipCurve::Hard => x.clamp(-S::ONE, S::ONE),
+        }
+    }
+}
+
+/// Saturates the input into `±1.0` with a selectable curve. `drive`
+/// scales the signal going into the curve, pushing it harder.
+pub struct SoftClip<const N: usize> {
+    curve: ClipCurve,
+    drive: f32,
+}
+
+impl<const N: usize> SoftClip<N> {
+    pub fn new(curve: ClipCurve, drive: f32) -> Self {
+        Self { curve, drive }
+    }
+    pub fn set_curve(&mut self, curve: ClipCurve) {
+        self.curve = curve;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for SoftClip<N> {
+    #[inline(always)]
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let drive = S::from_f32(self.drive);
+        for (out, input) in output.iter_mut().zip(&inputs[0]) {
+            for (out, &sample) in out[..ctx.frames()].iter_mut().zip(input.iter()) {
+                *out = self.curve.apply(sample * drive);
+            }
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("soft_clip")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("curve", Value::from_choice(self.curve))
+            .with("drive", self.drive)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "curve" => self.curve = value.choice()?,
+            "drive" => self.drive = value.float()?,
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
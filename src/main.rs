@@ -1,7 +1,9 @@
 use mini_graph::delay_line::DelayLine;
 use mini_graph::gain::Gain;
+use mini_graph::limiter::Limiter;
 use mini_graph::mixer::Mixer;
 use mini_graph::osc::{Oscillator, Wave};
+use mini_graph::reverb::Reverb;
 use mini_graph::write::*;
 use mini_graph::audio_graph::{DynamicAudioGraph};
 use assert_no_alloc::*;
@@ -22,6 +24,7 @@ where
     T: SizedSample + FromSample<f64>,
 {
     let mut audio_graph = DynamicAudioGraph::<FRAME_SIZE, CHANNEL_COUNT>::with_capacity(16);
+    audio_graph.set_sample_rate(SAMPLE_RATE);
     
     let master_id = audio_graph.add_node(Box::new(Mixer::default()));
 
@@ -43,7 +46,7 @@ where
     // ─── Mixer ───────────────────────────────────────────────────────────────────
     let chord_bus = audio_graph.add_node(Box::new(Mixer::default()));
 
-    let gain_id = audio_graph.add_node(Box::new(Gain::new(0.8))); // Some clipping limited to -1, 1
+    let gain_id = audio_graph.add_node(Box::new(Gain::new(0.8)));
 
     let delay_id = audio_graph.add_node(Box::new(DelayLine::new(12000)));
 
@@ -53,13 +56,27 @@ where
     audio_graph.add_edges(&[(osc_1, chord_bus),(osc_2, chord_bus),(osc_3, chord_bus),(osc_4,chord_bus), (chord_bus, gain_id), (gain_id, master_id)]);
 
     audio_graph.add_edges(&[(chord_bus, delay_id), (delay_id, delay_gain_id), (delay_gain_id, master_id)]);
+
+    // ─── Reverb ───────────────────────────────────────────────────────────────────
+    let mut reverb = Reverb::new(SAMPLE_RATE);
+    reverb.set_room_size(0.8);
+    reverb.set_pre_delay(20.0);
+    reverb.set_dry(0.0);
+    let reverb_id = audio_graph.add_node(Box::new(reverb));
+
+    audio_graph.add_edges(&[(chord_bus, reverb_id), (reverb_id, master_id)]);
+
+    // ─── Limiter ──────────────────────────────────────────────────────────────────
+    let limiter_id = audio_graph.add_node(Box::new(Limiter::new(SAMPLE_RATE, 5.0, 50.0, -0.3)));
+    audio_graph.add_edge(master_id, limiter_id);
+
     // ─── Sink ─────────────────────────────────────────────────────────────────────
-    audio_graph.set_sink_index(master_id);
+    audio_graph.set_sink_index(limiter_id);
 
     let stream = device.build_output_stream(
         config,
-        move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-            assert_no_alloc( || write_data::<FRAME_SIZE, CHANNEL_COUNT, f32>(data, &mut audio_graph))
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            assert_no_alloc( || write_data::<FRAME_SIZE, CHANNEL_COUNT, T, f32>(data, &mut audio_graph))
         },
         |err| eprintln!("An output stream error occured: {}", err),
         None,
@@ -86,7 +103,7 @@ fn main(){
         buffer_size: BufferSize::Fixed(FRAME_SIZE as u32),
     };
 
-    run::<FRAME_SIZE, f32>(&device, &config.into()).unwrap();
+    run::<FRAME_SIZE, f32>(&device, &config).unwrap();
 
     std::thread::park();
 }
\ No newline at end of file
@@ -1,20 +1,205 @@
+use core::f32::consts::FRAC_PI_2;
+
+use crate::audio_context::AudioContext;
 use crate::node::Node;
 use crate::buffer::Frame;
+use crate::param::{Choice, ParamError, Params, Value};
+use crate::sample::Sample;
+use crate::simd;
+
+/// How a mixer splits an input between the left and right channels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PanLaw {
+    /// Unity at the centre, fading out the far channel as a source moves
+    /// across, like a balance control.
+    #[default]
+    Balance,
+    /// -6 dB at the centre, quiet in the middle but sums to unity in mono.
+    Linear,
+    /// -3 dB at the centre, constant power as a source moves across.
+    EqualPower,
+    /// -4.5 dB at the centre, a compromise between the two.
+    Compromise,
+}
+
+impl Choice for PanLaw {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("balance", PanLaw::Balance),
+        ("linear", PanLaw::Linear),
+        ("equal_power", PanLaw::EqualPower),
+        ("compromise", PanLaw::Compromise),
+    ];
+}
+
+impl PanLaw {
+    /// The left and right gains for a pan position in `-1.0..=1.0`.
+    pub fn gains(&self, pan: f32) -> (f32, f32) {
+        let position = (pan.clamp(-1.0, 1.0) + 1.0) * 0.5;
+        let (left, right) = (1.0 - position, position);
+        let (cos, sin) = ((position * FRAC_PI_2).cos(), (position * FRAC_PI_2).sin());
+        match self {
+            PanLaw::Balance => ((left * 2.0).min(1.0), (right * 2.0).min(1.0)),
+            PanLaw::Linear => (left, right),
+            PanLaw::EqualPower => (cos, sin),
+            PanLaw::Compromise => ((left * cos).sqrt(), (right * sin).sqrt()),
+        }
+    }
+}
+
+/// What the sum of the inputs is divided by.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Normalisation {
+    /// Inputs are summed as is, like a hardware console, so adding an
+    /// input leaves the others' levels alone.
+    #[default]
+    Sum,
+    /// The sum is divided by the number of inputs that can be heard, so
+    /// adding inputs never raises the level.
+    Average,
+}
+
+impl Choice for Normalisation {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("sum", Normalisation::Sum),
+        ("average", Normalisation::Average),
+    ];
+}
 
+/// The settings of a single mixer input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MixerInput {
+    pub gain: f32,
+    /// From `-1.0` (left) to `1.0` (right), only used in stereo graphs.
+    pub pan: f32,
+    pub mute: bool,
+    pub solo: bool,
+}
+
+impl Default for MixerInput {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            pan: 0.0,
+            mute: false,
+            solo: false,
+        }
+    }
+}
+
+/// Sums every input into one output. Inputs are addressed by port, the
+/// order their edges were added to the graph, and any port that hasn't
+/// been configured plays at unity gain in the centre. The output is not
+/// clipped, so leave that to a limiter at the end of the chain.
 #[derive(Default)]
-pub struct Mixer<const N: usize> {}
+pub struct Mixer<const N: usize> {
+    inputs: Vec<MixerInput>,
+    pan_law: PanLaw,
+    normalisation: Normalisation,
+}
+
+impl<const N: usize> Mixer<N> {
+    /// A mixer with room for `inputs` configured ports.
+    pub fn with_inputs(inputs: usize) -> Self {
+        Self {
+            inputs: vec![MixerInput::default(); inputs],
+            ..Self::default()
+        }
+    }
+    pub fn set_pan_law(&mut self, pan_law: PanLaw) {
+        self.pan_law = pan_law;
+    }
+    pub fn set_normalisation(&mut self, normalisation: Normalisation) {
+        self.normalisation = normalisation;
+    }
+    pub fn input(&self, port: usize) -> MixerInput {
+        self.inputs.get(port).copied().unwrap_or_default()
+    }
+    pub fn input_mut(&mut self, port: usize) -> &mut MixerInput {
+        if port >= self.inputs.len() {
+            self.inputs.resize(port + 1, MixerInput::default());
+        }
+        &mut self.inputs[port]
+    }
+    pub fn set_gain(&mut self, port: usize, gain: f32) {
+        self.input_mut(port).gain = gain;
+    }
+    pub fn set_pan(&mut self, port: usize, pan: f32) {
+        self.input_mut(port).pan = pan;
+    }
+    pub fn set_mute(&mut self, port: usize, mute: bool) {
+        self.input_mut(port).mute = mute;
+    }
+    pub fn set_solo(&mut self, port: usize, solo: bool) {
+        self.input_mut(port).solo = solo;
+    }
+    #[inline(always)]
+    fn is_audible(&self, port: usize, solo_active: bool) -> bool {
+        let input = self.input(port);
+        !input.mute && (input.solo || !solo_active)
+    }
+}
 
-impl<const N: usize, const C: usize> Node<N, C> for Mixer<N> {
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Mixer<N> {
     #[inline(always)]
-    fn process(&mut self, inputs: &[Frame<N, C>], output: &mut Frame<N, C>){
-        for n in 0..N {
-            for c in 0..C {
-                let mut sum = 0.0;
-                for input in inputs {
-                    sum += input[c][n]
-                }
-                output[c][n] = (sum / inputs.len() as f32).clamp(-1.0, 1.0);
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>){
+        let frames = ctx.frames();
+        for out in output.iter_mut() {
+            simd::fill(&mut out[..frames], S::ZERO);
+        }
+
+        let solo_active = self.inputs.iter().any(|input| input.solo);
+        let audible = (0..inputs.len()).filter(|&port| self.is_audible(port, solo_active)).count();
+        if audible == 0 {
+            return;
+        }
+        let normalise = match self.normalisation {
+            Normalisation::Sum => 1.0,
+            Normalisation::Average => 1.0 / audible as f32,
+        };
+
+        for (port, input) in inputs.iter().enumerate() {
+            if !self.is_audible(port, solo_active) {
+                continue;
+            }
+            let settings = self.input(port);
+            let (left, right) = match C {
+                2 => self.pan_law.gains(settings.pan),
+                _ => (1.0, 1.0),
+            };
+            for (c, out) in output.iter_mut().enumerate() {
+                let pan = if c == 0 { left } else { right };
+                let gain = S::from_f32(settings.gain * pan * normalise);
+                simd::mul_add(&mut out[..frames], &input[c], gain);
             }
         }
     }
-}
\ No newline at end of file
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("mixer")
+    }
+
+    /// Per input settings are saved as lists indexed by port.
+    fn params(&self) -> Params {
+        let inputs = &self.inputs;
+        Params::new()
+            .with("pan_law", Value::from_choice(self.pan_law))
+            .with("normalisation", Value::from_choice(self.normalisation))
+            .with("gains", Value::from_floats(inputs.iter().map(|input| input.gain)))
+            .with("pans", Value::from_floats(inputs.iter().map(|input| input.pan)))
+            .with("mutes", Value::List(inputs.iter().map(|input| Value::Bool(input.mute)).collect()))
+            .with("solos", Value::List(inputs.iter().map(|input| Value::Bool(input.solo)).collect()))
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "pan_law" => self.pan_law = value.choice()?,
+            "normalisation" => self.normalisation = value.choice()?,
+            "gains" => value.floats()?.into_iter().enumerate().for_each(|(port, gain)| self.set_gain(port, gain)),
+            "pans" => value.floats()?.into_iter().enumerate().for_each(|(port, pan)| self.set_pan(port, pan)),
+            "mutes" => value.bools()?.into_iter().enumerate().for_each(|(port, mute)| self.set_mute(port, mute)),
+            "solos" => value.bools()?.into_iter().enumerate().for_each(|(port, solo)| self.set_solo(port, solo)),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,400 @@
+use core::f32::consts::{PI, TAU};
+
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::delay_line::DelayBuffer;
+use crate::node::Node;
+use crate::param::{ParamError, Params, Value};
+use crate::sample::Sample;
+
+const MAX_CHORUS_VOICES: usize = 8;
+const MAX_CHORUS_MS: f32 = 60.0;
+const MAX_FLANGER_MS: f32 = 20.0;
+const MAX_PHASER_STAGES: usize = 12;
+const MAX_FEEDBACK: f32 = 0.95;
+/// The highest graph sample rate the delay buffers are sized for, unless
+/// the node is made at a higher one.
+const MAX_SAMPLE_RATE: u32 = 192_000;
+
+/// The sine LFO sweeping each effect. Channels read it at their own
+/// phase offset, which spreads the effect across the stereo field.
+struct SweepLfo {
+    phase: f32,
+    rate: f32,
+}
+
+impl SweepLfo {
+    fn new(rate: f32) -> Self {
+        Self { phase: 0.0, rate }
+    }
+    /// From `-1.0` to `1.0`, `offset` turns ahead of the current phase.
+    #[inline(always)]
+    fn value(&self, offset: f32) -> f32 {
+        ((self.phase + offset) * TAU).sin()
+    }
+    #[inline(always)]
+    fn advance(&mut self, sample_rate: f32) {
+        self.phase += self.rate / sample_rate;
+        self.phase -= self.phase.floor();
+    }
+}
+
+/// A multi-voice chorus. Each voice reads the input through its own
+/// delay, swept by the LFO at an evenly spread phase, and the voices are
+/// averaged into the wet signal.
+///
+/// The delay buffers are allocated on construction, long enough for
+/// graphs running at up to 192 kHz or `sample_rate`, whichever is higher.
+pub struct Chorus<const N: usize, const C: usize, S: Sample = f32> {
+    lfo: SweepLfo,
+    buffers: Vec<DelayBuffer<S>>,
+    last: [S; C],
+    voices: usize,
+    delay_ms: f32,
+    depth_ms: f32,
+    feedback: f32,
+    mix: f32,
+    stereo_phase: f32,
+}
+
+impl<const N: usize, const C: usize, S: Sample> Chorus<N, C, S> {
+    pub fn new(sample_rate: u32) -> Self {
+        let max_delay = (MAX_CHORUS_MS * 2.0 * 0.001 * sample_rate.max(MAX_SAMPLE_RATE) as f32) as usize + 1;
+        Self {
+            lfo: SweepLfo::new(0.8),
+            buffers: (0..C).map(|_| DelayBuffer::new(max_delay)).collect(),
+            last: [S::ZERO; C],
+            voices: 3,
+            delay_ms: 15.0,
+            depth_ms: 4.0,
+            feedback: 0.0,
+            mix: 0.5,
+            stereo_phase: 0.25,
+        }
+    }
+    /// From one to eight voices.
+    pub fn set_voices(&mut self, voices: usize) {
+        self.voices = voices.clamp(1, MAX_CHORUS_VOICES);
+    }
+    /// How fast the delays sweep, in Hz.
+    pub fn set_rate(&mut self, hz: f32) {
+        self.lfo.rate = hz.max(0.0);
+    }
+    /// The delay the voices sweep around.
+    pub fn set_delay(&mut self, ms: f32) {
+        self.delay_ms = ms.clamp(0.0, MAX_CHORUS_MS);
+    }
+    /// How far either side of the delay the voices sweep.
+    pub fn set_depth(&mut self, ms: f32) {
+        self.depth_ms = ms.clamp(0.0, MAX_CHORUS_MS);
+    }
+    /// How much of the wet signal is fed back into the delays.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-MAX_FEEDBACK, MAX_FEEDBACK);
+    }
+    /// From `0.0`, only dry, to `1.0`, only wet.
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+    /// How far each channel's LFO runs ahead of the previous channel's, in
+    /// degrees.
+    pub fn set_stereo_phase(&mut self, degrees: f32) {
+        self.stereo_phase = degrees / 360.0;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Chorus<N, C, S> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let input = &inputs[0];
+        let to_samples = 0.001 * ctx.get_sample_rate();
+        let voice_gain = S::from_f32(1.0 / self.voices as f32);
+        let feedback = S::from_f32(self.feedback);
+        let (dry, wet) = (S::from_f32(1.0 - self.mix), S::from_f32(self.mix));
+
+        for n in 0..ctx.frames() {
+            for (c, buffer) in self.buffers.iter_mut().enumerate() {
+                let x = input[c][n];
+                buffer.push(x + self.last[c] * feedback);
+                let offset = c as f32 * self.stereo_phase;
+                let mut sum = S::ZERO;
+                for voice in 0..self.voices {
+                    let sweep = self.lfo.value(offset + voice as f32 / self.voices as f32);
+                    let delay = (self.delay_ms + self.depth_ms * sweep) * to_samples;
+                    sum += buffer.read_fractional(delay.max(1.0));
+                }
+                let sum = sum * voice_gain;
+                self.last[c] = sum;
+                output[c][n] = x * dry + sum * wet;
+            }
+            self.lfo.advance(*ctx.get_sample_rate());
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("chorus")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("voices", self.voices)
+            .with("rate", self.lfo.rate)
+            .with("delay", self.delay_ms)
+            .with("depth", self.depth_ms)
+            .with("feedback", self.feedback)
+            .with("mix", self.mix)
+            .with("stereo_phase", self.stereo_phase * 360.0)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "voices" => self.set_voices(value.count()?),
+            "rate" => self.set_rate(value.float()?),
+            "delay" => self.set_delay(value.float()?),
+            "depth" => self.set_depth(value.float()?),
+            "feedback" => self.set_feedback(value.float()?),
+            "mix" => self.set_mix(value.float()?),
+            "stereo_phase" => self.set_stereo_phase(value.float()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// A flanger: one short swept delay mixed with the dry signal, with
+/// feedback to sharpen the comb.
+///
+/// With through-zero enabled the dry signal is delayed by the depth as
+/// well, and the swept delay passes either side of it, so the notches
+/// sweep all the way down and cancel as the two cross. This adds `depth`
+/// of latency.
+///
+/// The delay buffers are sized like those of [`Chorus`].
+pub struct Flanger<const N: usize, const C: usize, S: Sample = f32> {
+    lfo: SweepLfo,
+    buffers: Vec<DelayBuffer<S>>,
+    last: [S; C],
+    delay_ms: f32,
+    depth_ms: f32,
+    feedback: f32,
+    mix: f32,
+    stereo_phase: f32,
+    through_zero: bool,
+}
+
+impl<const N: usize, const C: usize, S: Sample> Flanger<N, C, S> {
+    pub fn new(sample_rate: u32) -> Self {
+        let max_delay = (MAX_FLANGER_MS * 2.0 * 0.001 * sample_rate.max(MAX_SAMPLE_RATE) as f32) as usize + 1;
+        Self {
+            lfo: SweepLfo::new(0.25),
+            buffers: (0..C).map(|_| DelayBuffer::new(max_delay)).collect(),
+            last: [S::ZERO; C],
+            delay_ms: 1.0,
+            depth_ms: 3.0,
+            feedback: 0.5,
+            mix: 0.5,
+            stereo_phase: 0.0,
+            through_zero: false,
+        }
+    }
+    pub fn set_rate(&mut self, hz: f32) {
+        self.lfo.rate = hz.max(0.0);
+    }
+    /// The shortest delay of the sweep. Unused when through-zero.
+    pub fn set_delay(&mut self, ms: f32) {
+        self.delay_ms = ms.clamp(0.0, MAX_FLANGER_MS);
+    }
+    /// How far the delay sweeps.
+    pub fn set_depth(&mut self, ms: f32) {
+        self.depth_ms = ms.clamp(0.0, MAX_FLANGER_MS);
+    }
+    /// Negative feedback moves the notches up by half a step.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-MAX_FEEDBACK, MAX_FEEDBACK);
+    }
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+    pub fn set_stereo_phase(&mut self, degrees: f32) {
+        self.stereo_phase = degrees / 360.0;
+    }
+    pub fn set_through_zero(&mut self, through_zero: bool) {
+        self.through_zero = through_zero;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Flanger<N, C, S> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let input = &inputs[0];
+        let to_samples = 0.001 * ctx.get_sample_rate();
+        let depth = self.depth_ms * to_samples;
+        let delay = self.delay_ms * to_samples;
+        let feedback = S::from_f32(self.feedback);
+        let (dry_gain, wet_gain) = (S::from_f32(1.0 - self.mix), S::from_f32(self.mix));
+
+        for n in 0..ctx.frames() {
+            for (c, buffer) in self.buffers.iter_mut().enumerate() {
+                let x = input[c][n];
+                buffer.push(x + self.last[c] * feedback);
+                let sweep = self.lfo.value(c as f32 * self.stereo_phase);
+                let (dry, wet) = match self.through_zero {
+                    true => (buffer.read_fractional(depth), buffer.read_fractional(depth + depth * sweep)),
+                    false => (x, buffer.read_fractional(delay + depth * (sweep + 1.0) * 0.5)),
+                };
+                self.last[c] = wet;
+                output[c][n] = dry * dry_gain + wet * wet_gain;
+            }
+            self.lfo.advance(*ctx.get_sample_rate());
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("flanger")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("rate", self.lfo.rate)
+            .with("delay", self.delay_ms)
+            .with("depth", self.depth_ms)
+            .with("feedback", self.feedback)
+            .with("mix", self.mix)
+            .with("stereo_phase", self.stereo_phase * 360.0)
+            .with("through_zero", self.through_zero)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "rate" => self.set_rate(value.float()?),
+            "delay" => self.set_delay(value.float()?),
+            "depth" => self.set_depth(value.float()?),
+            "feedback" => self.set_feedback(value.float()?),
+            "mix" => self.set_mix(value.float()?),
+            "stereo_phase" => self.set_stereo_phase(value.float()?),
+            "through_zero" => self.set_through_zero(value.bool()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// A phaser: a chain of first order allpass filters whose corner sweeps
+/// exponentially between two frequencies. Mixed with the dry signal,
+/// every two stages add a notch.
+pub struct Phaser<const N: usize, const C: usize, S: Sample = f32> {
+    lfo: SweepLfo,
+    states: [[S; MAX_PHASER_STAGES]; C],
+    last: [S; C],
+    stages: usize,
+    min_hz: f32,
+    max_hz: f32,
+    depth: f32,
+    feedback: f32,
+    mix: f32,
+    stereo_phase: f32,
+}
+
+impl<const N: usize, const C: usize, S: Sample> Phaser<N, C, S> {
+    /// The phaser keeps no delay memory, so follows the graph's sample
+    /// rate whatever it is made with.
+    pub fn new(_sample_rate: u32) -> Self {
+        Self {
+            lfo: SweepLfo::new(0.5),
+            states: [[S::ZERO; MAX_PHASER_STAGES]; C],
+            last: [S::ZERO; C],
+            stages: 4,
+            min_hz: 200.0,
+            max_hz: 2_000.0,
+            depth: 1.0,
+            feedback: 0.3,
+            mix: 0.5,
+            stereo_phase: 0.25,
+        }
+    }
+    /// From one to twelve allpass stages.
+    pub fn set_stages(&mut self, stages: usize) {
+        self.stages = stages.clamp(1, MAX_PHASER_STAGES);
+    }
+    pub fn set_rate(&mut self, hz: f32) {
+        self.lfo.rate = hz.max(0.0);
+    }
+    /// The frequencies the corner sweeps between.
+    pub fn set_range(&mut self, min_hz: f32, max_hz: f32) {
+        self.min_hz = min_hz.max(1.0);
+        self.max_hz = max_hz.max(self.min_hz);
+    }
+    /// From `0.0` to `1.0`, how much of the range is swept, upwards from
+    /// the lowest frequency.
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth = depth.clamp(0.0, 1.0);
+    }
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-MAX_FEEDBACK, MAX_FEEDBACK);
+    }
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+    pub fn set_stereo_phase(&mut self, degrees: f32) {
+        self.stereo_phase = degrees / 360.0;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Phaser<N, C, S> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let input = &inputs[0];
+        let nyquist = ctx.get_sample_rate() * 0.5;
+        let ratio = self.max_hz.min(nyquist * 0.95) / self.min_hz;
+        let feedback = S::from_f32(self.feedback);
+        let (dry, wet) = (S::from_f32(1.0 - self.mix), S::from_f32(self.mix));
+
+        for n in 0..ctx.frames() {
+            for (c, states) in self.states.iter_mut().enumerate() {
+                let sweep = (self.lfo.value(c as f32 * self.stereo_phase) + 1.0) * 0.5;
+                let corner = (self.min_hz * ratio.powf(sweep * self.depth)).min(nyquist * 0.95);
+                let t = (PI * corner / ctx.get_sample_rate()).tan();
+                let coefficient = S::from_f32((t - 1.0) / (t + 1.0));
+
+                let x = input[c][n];
+                let mut y = x + self.last[c] * feedback;
+                for state in states[..self.stages].iter_mut() {
+                    let stage = coefficient * y + *state;
+                    *state = y - coefficient * stage;
+                    y = stage;
+                }
+                self.last[c] = y;
+                output[c][n] = x * dry + y * wet;
+            }
+            self.lfo.advance(*ctx.get_sample_rate());
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("phaser")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("stages", self.stages)
+            .with("rate", self.lfo.rate)
+            .with("min", self.min_hz)
+            .with("max", self.max_hz)
+            .with("depth", self.depth)
+            .with("feedback", self.feedback)
+            .with("mix", self.mix)
+            .with("stereo_phase", self.stereo_phase * 360.0)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "stages" => self.set_stages(value.count()?),
+            "rate" => self.set_rate(value.float()?),
+            "min" => self.set_range(value.float()?, self.max_hz),
+            "max" => self.set_range(self.min_hz, value.float()?),
+            "depth" => self.set_depth(value.float()?),
+            "feedback" => self.set_feedback(value.float()?),
+            "mix" => self.set_mix(value.float()?),
+            "stereo_phase" => self.set_stereo_phase(value.float()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,42 @@
+use crate::audio_context::AudioContext;
 use crate::buffer::Frame;
+use crate::param::{ParamError, Params, Value};
+use crate::sample::Sample;
 
-pub trait Node<const N: usize, const C: usize> {
-    fn process(&mut self, inputs: &[Frame<N, C>], output: &mut Frame<N, C>){}
+pub trait Node<const N: usize, const C: usize, S: Sample = f32> {
+    /// Computes the next `ctx.frames()` samples of `output` from `inputs`.
+    fn process(&mut self, _ctx: &AudioContext, _inputs: &[Frame<N, C, S>], _output: &mut Frame<N, C, S>){}
+
+    /// How many channels the node expects on input `port`. Sources with
+    /// a different count are mixed to fit by the edge between them.
+    fn input_channels(&self, _port: usize) -> usize {
+        C
+    }
+
+    /// How many channels the node writes, at most `C`. Channels past
+    /// this are left untouched.
+    fn output_channels(&self) -> usize {
+        C
+    }
+
+    /// The name the node's type is registered under in a
+    /// [`Registry`](crate::registry::Registry), or `None` for nodes that
+    /// can't be saved to a patch.
+    fn type_name(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// The node's settings, enough to rebuild it from a freshly
+    /// registered node with [`Node::set_param`].
+    fn params(&self) -> Params {
+        Params::new()
+    }
+
+    /// Changes a setting by name, as when loading a patch. Settings that
+    /// resize buffers allocate, so this is not for the audio thread.
+    fn set_param(&mut self, _name: &str, _value: &Value) -> Result<(), ParamError> {
+        Err(ParamError::Unknown)
+    }
 }
 
-pub type BoxedNode<const N: usize, const C: usize> = Box<dyn Node<N, C> + Send> ;
\ No newline at end of file
+pub type BoxedNode<const N: usize, const C: usize, S = f32> = Box<dyn Node<N, C, S> + Send> ;
@@ -0,0 +1,214 @@
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::node::Node;
+use crate::param::{Choice, ParamError, Params, Value};
+use crate::sample::Sample;
+
+/// A small xorshift64* generator. It is seeded explicitly and never
+/// allocates, so two generators with the same seed produce the same
+/// stream on every platform, which keeps offline renders and tests
+/// reproducible.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // SplitMix64 spreads similar seeds apart, and never lands on the
+        // all zero state xorshift can't leave.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Self { state: z.max(1) }
+    }
+    #[inline(always)]
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as u32
+    }
+    /// Uniform in `0.0..1.0`.
+    #[inline(always)]
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+    /// Uniform in `-1.0..1.0`.
+    #[inline(always)]
+    pub fn next_bipolar(&mut self) -> f32 {
+        self.next_f32() * 2.0 - 1.0
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NoiseColour {
+    /// Equal power at every frequency.
+    #[default]
+    White,
+    /// Falls 3 dB per octave, equal power in every octave.
+    Pink,
+    /// Falls 6 dB per octave, a random walk.
+    Brown,
+    /// Sparse impulses of random sign at random positions, one per
+    /// period of the density. Smoother than white noise at a fraction of
+    /// the density, and cheap to convolve with.
+    Velvet,
+}
+
+impl Choice for NoiseColour {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("white", NoiseColour::White),
+        ("pink", NoiseColour::Pink),
+        ("brown", NoiseColour::Brown),
+        ("velvet", NoiseColour::Velvet),
+    ];
+}
+
+/// Paul Kellet's refined pink filter, a sum of one pole lowpasses that
+/// stays within 0.05 dB of -3 dB per octave across the audible range.
+#[derive(Default)]
+struct PinkFilter {
+    b: [f32; 7],
+}
+
+impl PinkFilter {
+    #[inline(always)]
+    fn tick(&mut self, white: f32) -> f32 {
+        let b = &mut self.b;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        pink * 0.11
+    }
+}
+
+/// A mono noise source. Edges spread it to as many channels as the next
+/// node needs; use a node per channel with different seeds for
+/// uncorrelated stereo noise.
+pub struct Noise<const N: usize> {
+    colour: NoiseColour,
+    rng: Rng,
+    seed: u32,
+    amplitude: f32,
+    pink: PinkFilter,
+    brown: f32,
+    density: f32,
+    /// Samples until the next velvet period, and where in it the impulse
+    /// lands.
+    velvet_countdown: f32,
+    velvet_impulse: f32,
+}
+
+impl<const N: usize> Noise<N> {
+    pub fn new(colour: NoiseColour, seed: u32) -> Self {
+        Self {
+            colour,
+            rng: Rng::new(seed.into()),
+            seed,
+            amplitude: 1.0,
+            pink: PinkFilter::default(),
+            brown: 0.0,
+            density: 2_000.0,
+            velvet_countdown: 0.0,
+            velvet_impulse: -1.0,
+        }
+    }
+    pub fn set_colour(&mut self, colour: NoiseColour) {
+        self.colour = colour;
+    }
+    pub fn set_amplitude(&mut self, amplitude: f32) {
+        self.amplitude = amplitude;
+    }
+    /// Impulses per second of velvet noise.
+    pub fn set_density(&mut self, density: f32) {
+        self.density = density.max(1.0);
+    }
+    /// Restarts the stream from `seed`, clearing any filter state.
+    pub fn reseed(&mut self, seed: u32) {
+        *self = Self {
+            colour: self.colour,
+            amplitude: self.amplitude,
+            density: self.density,
+            ..Self::new(self.colour, seed)
+        };
+    }
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+    #[inline(always)]
+    fn tick(&mut self, sample_rate: f32) -> f32 {
+        match self.colour {
+            NoiseColour::White => self.rng.next_bipolar(),
+            NoiseColour::Pink => self.pink.tick(self.rng.next_bipolar()),
+            NoiseColour::Brown => {
+                // A slight leak keeps the walk from drifting off.
+                self.brown = (self.brown * 0.998 + self.rng.next_bipolar() * 0.0625).clamp(-1.0, 1.0);
+                self.brown
+            }
+            NoiseColour::Velvet => {
+                if self.velvet_countdown <= 0.0 {
+                    let period = sample_rate / self.density;
+                    self.velvet_countdown += period;
+                    self.velvet_impulse = (self.rng.next_f32() * period).floor();
+                }
+                self.velvet_countdown -= 1.0;
+                self.velvet_impulse -= 1.0;
+                match self.velvet_impulse == -1.0 {
+                    true if self.rng.next_u32() & 1 == 0 => 1.0,
+                    true => -1.0,
+                    false => 0.0,
+                }
+            }
+        }
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Noise<N> {
+    fn process(&mut self, ctx: &AudioContext, _: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let Some(out) = output.first_mut() else { return };
+        let sample_rate = *ctx.get_sample_rate();
+        for sample in out[..ctx.frames()].iter_mut() {
+            *sample = S::from_f32(self.tick(sample_rate) * self.amplitude);
+        }
+    }
+
+    fn output_channels(&self) -> usize {
+        1
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("noise")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("colour", Value::from_choice(self.colour))
+            .with("amplitude", self.amplitude)
+            .with("density", self.density)
+            .with("seed", self.seed)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "colour" => self.set_colour(value.choice()?),
+            "amplitude" => self.set_amplitude(value.float()?),
+            "density" => self.set_density(value.float()?),
+            "seed" => self.reseed(u32::try_from(value.count()?).map_err(|_| ParamError::Invalid)?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
+use crate::audio_context::AudioContext;
 use crate::buffer::{Frame};
 use crate::node::Node;
+use crate::param::{Choice, ParamError, Params, Value};
+use crate::sample::Sample;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Wave {
     SinWave,
     SawWave,
@@ -8,6 +12,15 @@ pub enum Wave {
     SquareWave,
 }
 
+impl Choice for Wave {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("sine", Wave::SinWave),
+        ("saw", Wave::SawWave),
+        ("triangle", Wave::TriangleWave),
+        ("square", Wave::SquareWave),
+    ];
+}
+
 pub struct Oscillator<const BUFFER_SIZE: usize> {
     freq: f32,
     sample_rate: f32,
@@ -35,20 +48,45 @@ impl<const N: usize> Oscillator<N> {
             Wave::SquareWave => square_amp_from_phase(&self.phase),
             Wave::TriangleWave => triangle_amp_from_phase(&self.phase),
         };
-        self.phase += self.freq / self.sample_rate as f32;
+        self.phase += self.freq / self.sample_rate;
         self.phase -= (self.phase >= 1.0) as u32 as f32; 
         sample
     }
 }
-impl<const N: usize, const C: usize> Node<N, C> for Oscillator<N> {
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Oscillator<N> {
     #[inline(always)]
-    fn process(&mut self, _: &[Frame<N, C>], output: &mut Frame<N, C>){
-        for i in 0..N {
-            let sample = self.tick_osc();
-            for buf in output.iter_mut() {
-                buf[i] = sample;
-            }
+    fn process(&mut self, ctx: &AudioContext, _: &[Frame<N, C, S>], output: &mut Frame<N, C, S>){
+        let Some(out) = output.first_mut() else { return };
+        for sample in out[..ctx.frames()].iter_mut() {
+            *sample = S::from_f32(self.tick_osc());
+        }
+    }
+
+    /// Oscillators are mono, edges spread them to as many channels as the
+    /// next node needs.
+    fn output_channels(&self) -> usize {
+        1
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("oscillator")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("freq", self.freq)
+            .with("wave", Value::from_choice(self.wave))
+            .with("phase", self.phase)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "freq" => self.freq = value.float()?,
+            "wave" => self.wave = value.choice()?,
+            "phase" => self.phase = value.float()?.rem_euclid(1.0),
+            _ => return Err(ParamError::Unknown),
         }
+        Ok(())
     }
 }
 
@@ -0,0 +1,272 @@
+use std::hint;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle, Thread};
+
+use crate::audio_context::AudioContext;
+use crate::audio_graph::{gather_inputs, Routing};
+use crate::buffer::Frame;
+use crate::node::BoxedNode;
+use crate::sample::Sample;
+
+/// How many times an idle worker polls for a new block before parking.
+/// Blocks arrive every few milliseconds, so a short spin keeps wake ups
+/// cheap without burning a core between callbacks.
+const SPIN_LIMIT: usize = 1 << 14;
+
+/// The `SCHED_FIFO` priority workers ask for, below the priority audio
+/// servers usually give their own callback thread.
+#[cfg(unix)]
+const WORKER_PRIORITY: i32 = 60;
+
+/// Everything a thread needs to compute one block. This lives on the
+/// stack of the audio thread for the duration of a single `next_block`,
+/// which waits for every worker before returning.
+pub(crate) struct Job<'a, const N: usize, const C: usize, S: Sample> {
+    pub ctx: &'a AudioContext,
+    pub routing: Routing<'a, C>,
+    pub nodes: *mut BoxedNode<N, C, S>,
+    pub outputs: *mut Frame<N, C, S>,
+    pub order: &'a [usize],
+    pub bounds: &'a [usize],
+}
+
+struct Shared<const N: usize, const C: usize, S: Sample> {
+    job: AtomicPtr<Job<'static, N, C, S>>,
+    epoch: AtomicUsize,
+    pending: AtomicUsize,
+    shutdown: AtomicBool,
+    claimed: Box<[AtomicUsize]>,
+    done: Box<[AtomicUsize]>,
+}
+
+/// A fixed pool of worker threads that compute independent branches of
+/// an audio graph. Nodes are grouped into dependency levels, and every
+/// thread, including the audio thread, claims nodes from the current
+/// level until it is exhausted. No locks are taken and nothing is
+/// allocated once the pool is running, and as every node still sees its
+/// inputs in the same order the output matches the serial executor.
+///
+/// Workers are moved to real time `SCHED_FIFO` scheduling. Where that
+/// isn't allowed, such as without an rtprio limit on Linux, or on
+/// platforms without it, they keep the default priority, and
+/// [`real_time_workers`](Self::real_time_workers) reports how many were
+/// promoted.
+pub struct WorkerPool<const N: usize, const C: usize, S: Sample = f32> {
+    shared: Arc<Shared<N, C, S>>,
+    threads: Vec<Thread>,
+    handles: Vec<JoinHandle<()>>,
+    real_time: usize,
+}
+
+impl<const N: usize, const C: usize, S: Sample> WorkerPool<N, C, S> {
+    /// Spawns `workers` threads for a graph of at most `capacity` nodes.
+    pub fn new(workers: usize, capacity: usize) -> Self {
+        let shared = Arc::new(Shared {
+            job: AtomicPtr::new(ptr::null_mut()),
+            epoch: AtomicUsize::new(0),
+            pending: AtomicUsize::new(0),
+            shutdown: AtomicBool::new(false),
+            claimed: (0..capacity).map(|_| AtomicUsize::new(0)).collect(),
+            done: (0..capacity).map(|_| AtomicUsize::new(0)).collect(),
+        });
+
+        let handles: Vec<JoinHandle<()>> = (0..workers)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || worker_loop(&shared, capacity))
+            })
+            .collect();
+        let threads = handles.iter().map(|handle| handle.thread().clone()).collect();
+        let real_time = handles.iter().filter(|handle| promote(handle)).count();
+
+        Self {
+            shared,
+            threads,
+            handles,
+            real_time,
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// How many workers run with real time scheduling.
+    pub fn real_time_workers(&self) -> usize {
+        self.real_time
+    }
+
+    /// Computes every level of the job, using `scratch` as the input
+    /// buffer of the calling thread.
+    pub(crate) fn run(&self, job: &Job<'_, N, C, S>, scratch: &mut Vec<Frame<N, C, S>>) {
+        let levels = job.bounds.len().saturating_sub(1);
+        for level in 0..levels {
+            self.shared.claimed[level].store(0, Ordering::Relaxed);
+            self.shared.done[level].store(0, Ordering::Relaxed);
+        }
+
+        self.shared.job.store(job as *const Job<'_, N, C, S> as *mut Job<'static, N, C, S>, Ordering::Relaxed);
+        self.shared.pending.store(self.threads.len(), Ordering::Relaxed);
+        self.shared.epoch.fetch_add(1, Ordering::Release);
+        for thread in &self.threads {
+            thread.unpark();
+        }
+
+        // SAFETY: the job outlives this call, and we wait below for
+        // every worker to stop touching it.
+        unsafe { run_levels(job, &self.shared, scratch) };
+
+        backoff_until(|| self.shared.pending.load(Ordering::Acquire) == 0);
+        self.shared.job.store(ptr::null_mut(), Ordering::Relaxed);
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Drop for WorkerPool<N, C, S> {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Release);
+        for thread in &self.threads {
+            thread.unpark();
+        }
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Asks for real time scheduling of a worker, returning whether it was
+/// granted.
+#[cfg(unix)]
+fn promote(handle: &JoinHandle<()>) -> bool {
+    use std::os::unix::thread::JoinHandleExt;
+    // SAFETY: the thread runs until the pool is dropped and joins it.
+    unsafe {
+        let min = libc::sched_get_priority_min(libc::SCHED_FIFO);
+        let max = libc::sched_get_priority_max(libc::SCHED_FIFO);
+        let mut param: libc::sched_param = std::mem::zeroed();
+        param.sched_priority = WORKER_PRIORITY.clamp(min, max);
+        libc::pthread_setschedparam(handle.as_pthread_t(), libc::SCHED_FIFO, &param) == 0
+    }
+}
+
+#[cfg(not(unix))]
+fn promote(_: &JoinHandle<()>) -> bool {
+    false
+}
+
+/// Spins until `ready` holds, yielding the core once the spin budget is
+/// spent so an oversubscribed machine can still schedule the thread we
+/// are waiting on.
+#[inline(always)]
+fn backoff_until(ready: impl Fn() -> bool) {
+    let mut spins = 0;
+    while !ready() {
+        if spins < SPIN_LIMIT {
+            spins += 1;
+            hint::spin_loop();
+        } else {
+            thread::yield_now();
+        }
+    }
+}
+
+fn worker_loop<const N: usize, const C: usize, S: Sample>(shared: &Shared<N, C, S>, capacity: usize) {
+    let mut scratch: Vec<Frame<N, C, S>> = Vec::with_capacity(capacity);
+    let mut seen = 0;
+    loop {
+        let mut spins = 0;
+        loop {
+            if shared.shutdown.load(Ordering::Acquire) {
+                return;
+            }
+            let epoch = shared.epoch.load(Ordering::Acquire);
+            if epoch != seen {
+                seen = epoch;
+                break;
+            }
+            if spins < SPIN_LIMIT {
+                spins += 1;
+                hint::spin_loop();
+            } else {
+                thread::park();
+            }
+        }
+
+        // SAFETY: the job is published before the epoch is bumped, and
+        // stays alive until `pending` drops to zero.
+        unsafe {
+            let job = &*shared.job.load(Ordering::Relaxed);
+            run_levels(job, shared, &mut scratch);
+        }
+        shared.pending.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// # Safety
+/// Each node index must appear once in `job.order`, and sources must
+/// always sit in an earlier level than the nodes reading them.
+unsafe fn run_levels<const N: usize, const C: usize, S: Sample>(
+    job: &Job<'_, N, C, S>,
+    shared: &Shared<N, C, S>,
+    scratch: &mut Vec<Frame<N, C, S>>,
+) {
+    for level in 0..job.bounds.len().saturating_sub(1) {
+        let nodes = &job.order[job.bounds[level]..job.bounds[level + 1]];
+        loop {
+            let claim = shared.claimed[level].fetch_add(1, Ordering::Relaxed);
+            if claim >= nodes.len() {
+                break;
+            }
+            let node_index = nodes[claim];
+
+            // Sources were finished in an earlier level, and this node is
+            // only ever claimed by a single thread.
+            let node = &mut *job.nodes.add(node_index);
+            gather_inputs(&job.routing, node_index, &**node, job.ctx.frames(), |src| &*job.outputs.add(src), scratch);
+            node.process(job.ctx, scratch, &mut *job.outputs.add(node_index));
+
+            shared.done[level].fetch_add(1, Ordering::Release);
+        }
+        backoff_until(|| shared.done[level].load(Ordering::Acquire) >= nodes.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio_graph::DynamicAudioGraph;
+    use crate::gain::Gain;
+    use crate::mixer::Mixer;
+    use crate::noise::{Noise, NoiseColour};
+    use crate::osc::{Oscillator, Wave};
+
+    /// Several branches of different depths feeding one mixer.
+    fn branches() -> DynamicAudioGraph<64, 2> {
+        let mut graph = DynamicAudioGraph::with_capacity(16);
+        let mixer = graph.add_node(Box::new(Mixer::default()));
+        for branch in 0..4 {
+            let mut last = match branch % 2 {
+                0 => graph.add_node(Box::new(Oscillator::new(110.0 * (branch + 1) as f32, 48_000, 0.0, Wave::SawWave))),
+                _ => graph.add_node(Box::new(Noise::new(NoiseColour::Pink, branch as u32))),
+            };
+            for _ in 0..branch {
+                let gain = graph.add_node(Box::new(Gain::new(0.9)));
+                graph.add_edge(last, gain);
+                last = gain;
+            }
+            graph.add_edge(last, mixer);
+        }
+        graph.set_sink_index(mixer);
+        graph
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let mut serial = branches();
+        let mut parallel = branches();
+        parallel.set_worker_threads(3);
+        for frames in [64, 17, 64, 1, 40] {
+            assert_eq!(serial.next_block(frames), parallel.next_block(frames));
+        }
+    }
+}
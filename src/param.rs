@@ -0,0 +1,215 @@
+/// A node setting, as read from or written to a patch.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Bool(bool),
+    Text(String),
+    List(Vec<Value>),
+}
+
+/// Why a node refused a setting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamError {
+    /// The node has no setting by that name.
+    Unknown,
+    /// The value is the wrong kind, such as text for a number.
+    WrongType,
+    /// The value is the right kind but can't be used, such as an unknown
+    /// choice or a formula that doesn't compile.
+    Invalid,
+}
+
+impl Value {
+    pub fn number(&self) -> Result<f64, ParamError> {
+        match self {
+            Value::Number(number) => Ok(*number),
+            _ => Err(ParamError::WrongType),
+        }
+    }
+    pub fn float(&self) -> Result<f32, ParamError> {
+        self.number().map(|number| number as f32)
+    }
+    /// A number rounded to the nearest whole, non-negative value.
+    pub fn count(&self) -> Result<usize, ParamError> {
+        self.number().map(|number| number.round().max(0.0) as usize)
+    }
+    pub fn bool(&self) -> Result<bool, ParamError> {
+        match self {
+            Value::Bool(value) => Ok(*value),
+            _ => Err(ParamError::WrongType),
+        }
+    }
+    pub fn text(&self) -> Result<&str, ParamError> {
+        match self {
+            Value::Text(text) => Ok(text),
+            _ => Err(ParamError::WrongType),
+        }
+    }
+    pub fn list(&self) -> Result<&[Value], ParamError> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(ParamError::WrongType),
+        }
+    }
+    /// A list of numbers.
+    pub fn floats(&self) -> Result<Vec<f32>, ParamError> {
+        self.list()?.iter().map(Value::float).collect()
+    }
+    /// A list of booleans.
+    pub fn bools(&self) -> Result<Vec<bool>, ParamError> {
+        self.list()?.iter().map(Value::bool).collect()
+    }
+    /// One of the named variants of `T`.
+    pub fn choice<T: Choice>(&self) -> Result<T, ParamError> {
+        let name = self.text()?;
+        T::CHOICES
+            .iter()
+            .find(|(choice, _)| *choice == name)
+            .map(|&(_, value)| value)
+            .ok_or(ParamError::Invalid)
+    }
+    /// The name of a variant of `T`.
+    pub fn from_choice<T: Choice>(value: T) -> Self {
+        let name = T::CHOICES
+            .iter()
+            .find(|(_, choice)| *choice == value)
+            .map_or("", |&(name, _)| name);
+        Value::Text(name.to_string())
+    }
+    pub fn from_floats(values: impl IntoIterator<Item = f32>) -> Self {
+        Value::List(values.into_iter().map(Value::from).collect())
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+/// An enum whose variants are saved by name.
+pub trait Choice: Copy + PartialEq + 'static {
+    const CHOICES: &'static [(&'static str, Self)];
+}
+
+/// A node's settings in order, as saved to a patch.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Params(Vec<(String, Value)>);
+
+impl Params {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+    /// Adds a setting, for building a list of settings in one expression.
+    pub fn with(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.push(name, value);
+        self
+    }
+    pub fn push(&mut self, name: &str, value: impl Into<Value>) {
+        self.0.push((name.to_string(), value.into()));
+    }
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.iter().find(|(key, _)| key == name).map(|(_, value)| value)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), value))
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// What a setting accepts, for UIs and scripting frontends to offer the
+/// right control. Number ranges are what a control should span; nodes
+/// clamp values to their own limits when set.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamKind {
+    Number { min: f64, max: f64 },
+    /// A number rounded to a whole value.
+    Integer { min: f64, max: f64 },
+    Bool,
+    /// One of a fixed set of words.
+    Choice(Vec<&'static str>),
+    Text,
+    /// A list of values of one kind.
+    List(Box<ParamKind>),
+    /// A value of any one of several kinds.
+    OneOf(Vec<ParamKind>),
+}
+
+impl ParamKind {
+    pub fn number(min: f64, max: f64) -> Self {
+        ParamKind::Number { min, max }
+    }
+    pub fn integer(min: f64, max: f64) -> Self {
+        ParamKind::Integer { min, max }
+    }
+    /// The names of the variants of `T`.
+    pub fn choice<T: Choice>() -> Self {
+        ParamKind::Choice(T::CHOICES.iter().map(|&(name, _)| name).collect())
+    }
+    pub fn list(kind: ParamKind) -> Self {
+        ParamKind::List(Box::new(kind))
+    }
+    /// Whether `value` is of this kind. Ranges aren't checked.
+    pub fn accepts(&self, value: &Value) -> bool {
+        match (self, value) {
+            (ParamKind::Number { .. } | ParamKind::Integer { .. }, Value::Number(_)) => true,
+            (ParamKind::Bool, Value::Bool(_)) => true,
+            (ParamKind::Choice(choices), Value::Text(text)) => choices.contains(&text.as_str()),
+            (ParamKind::Text, Value::Text(_)) => true,
+            (ParamKind::List(kind), Value::List(values)) => values.iter().all(|value| kind.accepts(value)),
+            (ParamKind::OneOf(kinds), value) => kinds.iter().any(|kind| kind.accepts(value)),
+            _ => false,
+        }
+    }
+}
+
+/// Describes one setting of a node type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    /// The value a newly built node has, filled in by the
+    /// [`Registry`](crate::registry::Registry) from the node itself.
+    pub default: Option<Value>,
+}
+
+impl ParamSpec {
+    pub fn new(name: &'static str, kind: ParamKind) -> Self {
+        Self { name, kind, default: None }
+    }
+}
@@ -0,0 +1,621 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::audio_graph::DynamicAudioGraph;
+use crate::channel::ChannelMap;
+use crate::graph::{DynamicGraph, Graph};
+use crate::param::{ParamError, Params, Value};
+use crate::registry::{Registry, RegistryError};
+use crate::sample::Sample;
+
+#[derive(Debug)]
+pub enum PatchError {
+    Io(io::Error),
+    /// A line that couldn't be read, counted from one.
+    Syntax { line: usize, message: String },
+    /// A node type missing from the registry.
+    UnknownType(String),
+    /// An edge, sink or bus naming a node the patch doesn't define.
+    UnknownNode(String),
+    DuplicateNode(String),
+    /// A node refused one of its settings.
+    Param { node: String, param: String, error: ParamError },
+    /// A channel map with more rows or columns than the graph has
+    /// channels.
+    ChannelMap { from: String, to: String },
+    /// The edges form a loop, which a graph can't compute.
+    Cycle,
+    /// The node at this index has no type name, so it can't be saved.
+    Unsaveable(usize),
+}
+
+impl From<io::Error> for PatchError {
+    fn from(error: io::Error) -> Self {
+        PatchError::Io(error)
+    }
+}
+
+/// A node of a [`Patch`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatchNode {
+    pub id: String,
+    pub type_name: String,
+    pub params: Params,
+}
+
+/// An edge of a [`Patch`]. Edges into the same node are listed in port
+/// order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatchEdge {
+    pub from: String,
+    pub to: String,
+    /// The rows of a [`ChannelMap::Matrix`], or `None` for the automatic
+    /// rules.
+    pub map: Option<Vec<Vec<f32>>>,
+}
+
+/// A graph described by node types and settings rather than code, which
+/// can be loaded into a [`DynamicAudioGraph`] and saved back out.
+///
+/// Patches are written one statement per line, with `#` starting a
+/// comment:
+///
+/// ```text
+/// sample_rate 48000
+/// node osc = oscillator freq=220 wave=saw
+/// node lfo = lfo hz=0.5 polarity=unipolar
+/// node vca = multiply a=in0 b=in1
+/// node out = limiter ceiling=-1
+/// edge osc -> vca
+/// edge lfo -> vca
+/// edge vca -> out map=[[0.5, 0.5], [0.5, 0.5]]
+/// sink out
+/// bus dry = vca
+/// ```
+///
+/// Settings are numbers, including `inf`, `-inf` and `nan`, `true` or
+/// `false`, words or `"quoted text"` for choices and text, and `[lists]`
+/// of any of these.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Patch {
+    pub sample_rate: u32,
+    pub nodes: Vec<PatchNode>,
+    pub edges: Vec<PatchEdge>,
+    pub sink: Option<String>,
+    /// Named output buses, as a bus name and the id of the node it
+    /// carries.
+    pub buses: Vec<(String, String)>,
+}
+
+impl Default for Patch {
+    fn default() -> Self {
+        Self {
+            sample_rate: 48_000,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            sink: None,
+            buses: Vec::new(),
+        }
+    }
+}
+
+impl Patch {
+    pub fn parse(text: &str) -> Result<Self, PatchError> {
+        let mut patch = Patch::default();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let syntax = |message: &str| PatchError::Syntax { line: line_number, message: message.to_string() };
+            let mut tokens = Tokens::new(line).map_err(|message| syntax(&message))?;
+            let Some(keyword) = tokens.next() else { continue };
+            match keyword {
+                Token::Word(word) if word == "sample_rate" => {
+                    patch.sample_rate = match tokens.next() {
+                        Some(Token::Number(rate)) if rate >= 1.0 => rate as u32,
+                        _ => return Err(syntax("expected a sample rate")),
+                    };
+                }
+                Token::Word(word) if word == "node" => {
+                    let id = tokens.name().ok_or_else(|| syntax("expected a node id"))?;
+                    if tokens.next() != Some(Token::Equals) {
+                        return Err(syntax("expected `=` after the node id"));
+                    }
+                    let type_name = tokens.name().ok_or_else(|| syntax("expected a node type"))?;
+                    let params = tokens.settings().map_err(|message| syntax(&message))?;
+                    if patch.nodes.iter().any(|node| node.id == id) {
+                        return Err(PatchError::DuplicateNode(id));
+                    }
+                    patch.nodes.push(PatchNode { id, type_name, params });
+                }
+                Token::Word(word) if word == "edge" => {
+                    let from = tokens.name().ok_or_else(|| syntax("expected a source node"))?;
+                    if tokens.next() != Some(Token::Arrow) {
+                        return Err(syntax("expected `->` between the nodes of an edge"));
+                    }
+                    let to = tokens.name().ok_or_else(|| syntax("expected a destination node"))?;
+                    let settings = tokens.settings().map_err(|message| syntax(&message))?;
+                    let mut map = None;
+                    for (name, value) in settings.iter() {
+                        match name {
+                            "map" => map = Some(matrix(value).ok_or_else(|| syntax("expected a map of rows of gains"))?),
+                            _ => return Err(syntax(&format!("unknown edge setting `{name}`"))),
+                        }
+                    }
+                    patch.edges.push(PatchEdge { from, to, map });
+                }
+                Token::Word(word) if word == "sink" => {
+                    patch.sink = Some(tokens.name().ok_or_else(|| syntax("expected a sink node"))?);
+                }
+                Token::Word(word) if word == "bus" => {
+                    let name = tokens.name().ok_or_else(|| syntax("expected a bus name"))?;
+                    if tokens.next() != Some(Token::Equals) {
+                        return Err(syntax("expected `=` after the bus name"));
+                    }
+                    let node = tokens.name().ok_or_else(|| syntax("expected the node the bus carries"))?;
+                    patch.buses.push((name, node));
+                }
+                _ => return Err(syntax("expected `sample_rate`, `node`, `edge`, `sink` or `bus`")),
+            }
+            if tokens.next().is_some() {
+                return Err(syntax("unexpected text at the end of the line"));
+            }
+        }
+        Ok(patch)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PatchError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PatchError> {
+        Ok(fs::write(path, self.to_string())?)
+    }
+
+    /// Describes `graph`, naming each node by its
+    /// [`node_name`](DynamicAudioGraph::node_name), or `n` and its index
+    /// when it has none. Every node needs a type name.
+    pub fn from_graph<const N: usize, const C: usize, S: Sample>(graph: &DynamicAudioGraph<N, C, S>) -> Result<Self, PatchError> {
+        let id = |node: usize| graph.node_name(node).map_or_else(|| format!("n{node}"), str::to_string);
+        let mut patch = Patch {
+            sample_rate: *graph.context().get_sample_rate() as u32,
+            sink: (graph.node_count() > 0).then(|| id(graph.sink_index())),
+            ..Patch::default()
+        };
+        for index in 0..graph.node_count() {
+            let node = graph.node(index);
+            let type_name = node.type_name().ok_or(PatchError::Unsaveable(index))?;
+            patch.nodes.push(PatchNode {
+                id: id(index),
+                type_name: type_name.to_string(),
+                params: node.params(),
+            });
+            for from in graph.incoming(index) {
+                let map = match graph.channel_map(from, index) {
+                    ChannelMap::Auto => None,
+                    ChannelMap::Matrix(gains) => Some(gains.iter().map(|row| row.to_vec()).collect()),
+                };
+                patch.edges.push(PatchEdge { from: id(from), to: id(index), map });
+            }
+        }
+        for bus in 0..graph.bus_count() {
+            patch.buses.push((graph.bus_name(bus).to_string(), id(graph.bus_node(bus))));
+        }
+        Ok(patch)
+    }
+
+    /// Builds the patch into a new graph with room for exactly its nodes,
+    /// each named by its id.
+    pub fn build<const N: usize, const C: usize, S: Sample>(&self, registry: &Registry<N, C, S>) -> Result<DynamicAudioGraph<N, C, S>, PatchError> {
+        let index = |id: &str| {
+            self.nodes
+                .iter()
+                .position(|node| node.id == id)
+                .ok_or_else(|| PatchError::UnknownNode(id.to_string()))
+        };
+        let mut edges = Vec::with_capacity(self.edges.len());
+        for edge in &self.edges {
+            let map = match &edge.map {
+                None => ChannelMap::Auto,
+                Some(rows) if rows.len() <= C && rows.iter().all(|row| row.len() <= C) => {
+                    let mut gains = [[0.0; C]; C];
+                    for (gains, row) in gains.iter_mut().zip(rows) {
+                        gains[..row.len()].copy_from_slice(row);
+                    }
+                    ChannelMap::Matrix(gains)
+                }
+                Some(_) => return Err(PatchError::ChannelMap { from: edge.from.clone(), to: edge.to.clone() }),
+            };
+            edges.push((index(&edge.from)?, index(&edge.to)?, map));
+        }
+        let sink = self.sink.as_deref().map(index).transpose()?;
+        let buses = self
+            .buses
+            .iter()
+            .map(|(name, node)| Ok((name, index(node)?)))
+            .collect::<Result<Vec<_>, PatchError>>()?;
+
+        // The graph panics on a cycle, so check the edges beforehand.
+        let mut check = DynamicGraph::with_capacity(self.nodes.len());
+        for _ in &self.nodes {
+            check.add_node(());
+        }
+        for &(from, to, _) in &edges {
+            check.add_edge(from, to);
+        }
+        check.topo_sort().map_err(|_| PatchError::Cycle)?;
+
+        let mut graph = DynamicAudioGraph::with_capacity(self.nodes.len().max(1));
+        graph.set_sample_rate(self.sample_rate);
+        for node in &self.nodes {
+            let built = registry
+                .create_with(&node.type_name, self.sample_rate, &node.params)
+                .map_err(|error| match error {
+                    RegistryError::UnknownType(type_name) => PatchError::UnknownType(type_name),
+                    RegistryError::Param { param, error } => PatchError::Param { node: node.id.clone(), param, error },
+                })?;
+            // Inlets feed the graph's inputs from the port they name.
+            let port = match built.type_name() {
+                Some("input") => built.params().get("port").and_then(|port| port.count().ok()),
+                _ => None,
+            };
+            let index = graph.add_node(built);
+            graph.set_node_name(index, &node.id);
+            if let Some(port) = port {
+                graph.set_input(port, index);
+            }
+        }
+        for (from, to, map) in edges {
+            graph.set_channel_map(from, to, map);
+            graph.add_edge(from, to);
+        }
+        if let Some(sink) = sink {
+            graph.set_sink_index(sink);
+        }
+        for (name, node) in buses {
+            graph.add_bus(name, node);
+        }
+        Ok(graph)
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "sample_rate {}", self.sample_rate)?;
+        for node in &self.nodes {
+            write!(f, "node {} = {}", Name(&node.id), Name(&node.type_name))?;
+            for (name, value) in node.params.iter() {
+                write!(f, " {}={}", Name(name), Written(value))?;
+            }
+            writeln!(f)?;
+        }
+        for edge in &self.edges {
+            write!(f, "edge {} -> {}", Name(&edge.from), Name(&edge.to))?;
+            if let Some(rows) = &edge.map {
+                let rows = rows.iter().map(|row| Value::from_floats(row.iter().copied())).collect();
+                write!(f, " map={}", Written(&Value::List(rows)))?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(sink) = &self.sink {
+            writeln!(f, "sink {}", Name(sink))?;
+        }
+        for (name, node) in &self.buses {
+            writeln!(f, "bus {} = {}", Name(name), Name(node))?;
+        }
+        Ok(())
+    }
+}
+
+/// The rows of a channel map.
+fn matrix(value: &Value) -> Option<Vec<Vec<f32>>> {
+    value.list().ok()?.iter().map(|row| row.floats().ok()).collect()
+}
+
+fn is_word(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Writes text in quotes, escaped the way [`Tokens`] reads it.
+fn quote(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => write!(f, "\\{c}")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// An id or type name, quoted when it isn't a plain word.
+struct Name<'a>(&'a str);
+
+impl fmt::Display for Name<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match is_word(self.0) {
+            true => f.write_str(self.0),
+            false => quote(f, self.0),
+        }
+    }
+}
+
+/// A setting as it appears in a patch.
+pub(crate) struct Written<'a>(pub(crate) &'a Value);
+
+impl fmt::Display for Written<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::Number(number) if number.is_nan() => f.write_str("nan"),
+            &Value::Number(number) if number.is_infinite() => f.write_str(if number > 0.0 { "inf" } else { "-inf" }),
+            &Value::Number(number) => write_number(f, number),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Text(text) if is_word(text) && !matches!(text.as_str(), "true" | "false" | "inf" | "nan") => f.write_str(text),
+            Value::Text(text) => quote(f, text),
+            Value::List(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", Written(value))?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+/// Writes a finite number, whole numbers exactly, and numbers that came
+/// from an `f32` as the shortest text that reads back the same, so `0.1`
+/// isn't padded out to seventeen digits.
+pub(crate) fn write_number(out: &mut impl fmt::Write, number: f64) -> fmt::Result {
+    if number.fract() == 0.0 && number.abs() < 9e15 {
+        write!(out, "{}", number as i64)
+    } else if number as f32 as f64 == number {
+        write!(out, "{}", number as f32)
+    } else {
+        write!(out, "{number}")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Number(f64),
+    Equals,
+    Arrow,
+    Open,
+    Close,
+    Comma,
+}
+
+/// The tokens of one line, up to any comment.
+struct Tokens {
+    tokens: std::vec::IntoIter<Token>,
+    peeked: Option<Token>,
+}
+
+impl Tokens {
+    fn new(line: &str) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        let mut chars = line.char_indices().peekable();
+        while let Some(&(start, c)) = chars.peek() {
+            match c {
+                '#' => break,
+                c if c.is_whitespace() => {
+                    chars.next();
+                }
+                '=' | '[' | ']' | ',' => {
+                    chars.next();
+                    tokens.push(match c {
+                        '=' => Token::Equals,
+                        '[' => Token::Open,
+                        ']' => Token::Close,
+                        _ => Token::Comma,
+                    });
+                }
+                '-' if line[start..].starts_with("->") => {
+                    chars.nth(1);
+                    tokens.push(Token::Arrow);
+                }
+                '-' if line[start..].starts_with("-inf") && !line[start + 4..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.') => {
+                    chars.nth(3);
+                    tokens.push(Token::Number(f64::NEG_INFINITY));
+                }
+                '"' => {
+                    chars.next();
+                    let mut text = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '"')) => break,
+                            Some((_, '\\')) => match chars.next() {
+                                Some((_, 'n')) => text.push('\n'),
+                                Some((_, 't')) => text.push('\t'),
+                                Some((_, c)) => text.push(c),
+                                None => return Err("unterminated text".to_string()),
+                            },
+                            Some((_, c)) => text.push(c),
+                            None => return Err("unterminated text".to_string()),
+                        }
+                    }
+                    tokens.push(Token::Text(text));
+                }
+                c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                    let mut end = start;
+                    let mut previous = ' ';
+                    while let Some(&(at, c)) = chars.peek() {
+                        let sign = (c == '-' || c == '+') && (at == start || previous == 'e' || previous == 'E');
+                        if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || sign) {
+                            break;
+                        }
+                        previous = c;
+                        end = at + c.len_utf8();
+                        chars.next();
+                    }
+                    let number = line[start..end].parse().map_err(|_| format!("`{}` is not a number", &line[start..end]))?;
+                    tokens.push(Token::Number(number));
+                }
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    let mut end = start;
+                    // Dots join the id and setting of a node inside a subgraph.
+                    while let Some(&(at, c)) = chars.peek() {
+                        if !(c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                            break;
+                        }
+                        end = at + 1;
+                        chars.next();
+                    }
+                    tokens.push(Token::Word(line[start..end].to_string()));
+                }
+                c => return Err(format!("unexpected `{c}`")),
+            }
+        }
+        Ok(Self { tokens: tokens.into_iter(), peeked: None })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.peeked.take().or_else(|| self.tokens.next())
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    /// An id or type name, as a word or quoted text.
+    fn name(&mut self) -> Option<String> {
+        match self.next()? {
+            Token::Word(name) | Token::Text(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// `name=value` pairs up to the end of the line.
+    fn settings(&mut self) -> Result<Params, String> {
+        let mut params = Params::new();
+        while self.peek().is_some() {
+            let name = self.name().ok_or("expected a setting name")?;
+            if self.next() != Some(Token::Equals) {
+                return Err(format!("expected `=` after `{name}`"));
+            }
+            params.push(&name, self.value()?);
+        }
+        Ok(params)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Value::Number(number)),
+            Some(Token::Word(word)) => Ok(match word.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "inf" => Value::Number(f64::INFINITY),
+                "nan" => Value::Number(f64::NAN),
+                _ => Value::Text(word),
+            }),
+            Some(Token::Text(text)) => Ok(Value::Text(text)),
+            Some(Token::Open) => {
+                let mut values = Vec::new();
+                loop {
+                    match self.peek() {
+                        Some(Token::Close) => {
+                            self.next();
+                            return Ok(Value::List(values));
+                        }
+                        Some(Token::Comma) => {
+                            self.next();
+                        }
+                        Some(_) => values.push(self.value()?),
+                        None => return Err("unterminated list".to_string()),
+                    }
+                }
+            }
+            _ => Err("expected a value".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Patch, PatchError};
+    use crate::param::Value;
+    use crate::registry::Registry;
+
+    const TEXT: &str = r#"
+# A comment line.
+sample_rate 44100
+node "lead osc" = oscillator frequency=220 wave=SawWave
+node g = gain gain=0.25  # and one after a node
+node "say \"hi\"" = gain gain=-1.5e-3
+node odd = custom high=inf low=-inf unset=nan flag=true name="two words" word=true_ish list=[1, -2.5, [3]]
+edge "lead osc" -> g map=[[1, 0], [0, 0.5]]
+edge g -> "say \"hi\""
+sink "say \"hi\""
+bus "send a" = g
+"#;
+
+    #[test]
+    fn parse() {
+        let patch = Patch::parse(TEXT).unwrap();
+        assert_eq!(patch.sample_rate, 44_100);
+        assert_eq!(patch.nodes[0].id, "lead osc");
+        assert_eq!(patch.nodes[2].id, "say \"hi\"");
+        assert_eq!(patch.nodes[2].params.get("gain"), Some(&Value::Number(-1.5e-3)));
+        let odd = &patch.nodes[3].params;
+        assert_eq!(odd.get("high"), Some(&Value::Number(f64::INFINITY)));
+        assert_eq!(odd.get("low"), Some(&Value::Number(f64::NEG_INFINITY)));
+        assert!(matches!(odd.get("unset"), Some(Value::Number(number)) if number.is_nan()));
+        assert_eq!(odd.get("flag"), Some(&Value::Bool(true)));
+        assert_eq!(odd.get("name"), Some(&Value::from("two words")));
+        assert_eq!(odd.get("word"), Some(&Value::from("true_ish")));
+        assert_eq!(patch.edges[0].map, Some(vec![vec![1.0, 0.0], vec![0.0, 0.5]]));
+        assert_eq!(patch.sink.as_deref(), Some("say \"hi\""));
+        assert_eq!(patch.buses, [("send a".to_string(), "g".to_string())]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = Patch::parse(TEXT).unwrap().to_string();
+        assert_eq!(Patch::parse(&text).unwrap().to_string(), text);
+        // Text that reads as a keyword or number stays text.
+        let patch = Patch::parse("node a = b x=\"inf\" y=\"true\" z=\"1\"").unwrap();
+        assert_eq!(Patch::parse(&patch.to_string()).unwrap(), patch);
+    }
+
+    #[test]
+    fn errors() {
+        let line = |text: &str| match Patch::parse(text) {
+            Err(PatchError::Syntax { line, .. }) => line,
+            other => panic!("expected a syntax error, got {other:?}"),
+        };
+        assert_eq!(line("node a = b\nedge a b"), 2);
+        assert_eq!(line("sample_rate 0"), 1);
+        assert_eq!(line("\n\nnode a = b x=\"open"), 3);
+        assert_eq!(line("node a = b x=1 y"), 1);
+        assert_eq!(line("edge a -> b gain=1"), 1);
+        assert!(matches!(Patch::parse("node a = b\nnode a = c"), Err(PatchError::DuplicateNode(id)) if id == "a"));
+    }
+
+    fn render(text: &str) -> f32 {
+        let mut graph = Patch::parse(text).unwrap().build(&Registry::<64, 2>::default()).unwrap();
+        graph.next_block(64)[0][63]
+    }
+
+    #[test]
+    fn builds_without_edges() {
+        assert_eq!(render("node c = constant value=0.5\nsink c"), 0.5);
+    }
+
+    #[test]
+    fn unconnected_effect_is_silent() {
+        assert_eq!(render("node g = gain\nsink g"), 0.0);
+    }
+}
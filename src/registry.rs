@@ -0,0 +1,421 @@
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+
+use crate::cv::{Abs, Constant, Max, Min, Multiply, OffsetScale, Operand, Quantizer, RangeMap, SampleAndHold, Scale, Slew, Sum};
+use crate::delay_line::DelayLine;
+use crate::dynamics::{Detection, Dynamics};
+use crate::expr::Expr;
+use crate::gain::Gain;
+use crate::lfo::{Lfo, LfoRate, LfoShape, Polarity};
+use crate::limiter::{ClipCurve, Limiter, SoftClip};
+use crate::mixer::{Mixer, Normalisation, PanLaw};
+use crate::modulation::{Chorus, Flanger, Phaser};
+use crate::node::BoxedNode;
+use crate::noise::{Noise, NoiseColour};
+use crate::osc::{Oscillator, Wave};
+use crate::param::{Choice, ParamError, ParamKind, ParamSpec, Params};
+use crate::patch::PatchError;
+use crate::reverb::Reverb;
+use crate::routing::{Merger, Router, Splitter};
+use crate::sample::Sample;
+use crate::sequencer::{Clock, ClockDivider, ClockMultiplier, Direction, Euclidean, Sequencer};
+use crate::stereo::{Balance, Pan, StereoWidth};
+use crate::subgraph::{Inlet, SubgraphTemplate};
+use crate::waveshaper::{Bitcrusher, Oversampling, Shape, Waveshaper};
+
+/// The sample rate nodes are built at to read their default settings.
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+
+/// Builds a node with its default settings at a sample rate.
+pub type Factory<const N: usize, const C: usize, S> = Arc<dyn Fn(u32) -> BoxedNode<N, C, S> + Send + Sync>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegistryError {
+    UnknownType(String),
+    /// The node refused one of the settings it was built with.
+    Param { param: String, error: ParamError },
+}
+
+struct NodeType<const N: usize, const C: usize, S> {
+    factory: Factory<N, C, S>,
+    params: Vec<ParamSpec>,
+}
+
+impl<const N: usize, const C: usize, S> Clone for NodeType<N, C, S> {
+    fn clone(&self) -> Self {
+        Self { factory: self.factory.clone(), params: self.params.clone() }
+    }
+}
+
+/// Maps the type names nodes report from
+/// [`Node::type_name`](crate::node::Node::type_name) to factories and
+/// descriptions of their settings, so patch loaders, UIs and scripting
+/// frontends can list the nodes available and build them by name.
+/// Settings are applied after construction with
+/// [`Node::set_param`](crate::node::Node::set_param).
+///
+/// Other crates add their own nodes with [`Registry::register`]:
+///
+/// ```ignore
+/// let mut registry = Registry::<512, 2>::default();
+/// registry.register(
+///     "tremolo",
+///     vec![ParamSpec::new("rate", ParamKind::number(0.1, 20.0))],
+///     |sample_rate| Box::new(Tremolo::new(sample_rate)),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Registry<const N: usize, const C: usize, S: Sample = f32> {
+    types: IndexMap<String, NodeType<N, C, S>>,
+}
+
+impl<const N: usize, const C: usize, S: Sample> Registry<N, C, S> {
+    /// A registry with no node types.
+    pub fn new() -> Self {
+        Self { types: IndexMap::new() }
+    }
+
+    /// A registry with every node type in this crate that can be saved.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(
+            "oscillator",
+            vec![number("freq", 0.0, 20_000.0), choice::<Wave>("wave"), number("phase", 0.0, 1.0)],
+            |sample_rate| Box::new(Oscillator::new(440.0, sample_rate, 0.0, Wave::SinWave)),
+        );
+        registry.register("gain", vec![number("gain", 0.0, 2.0)], |_| Box::new(Gain::new(1.0)));
+        registry.register(
+            "mixer",
+            vec![
+                choice::<PanLaw>("pan_law"),
+                choice::<Normalisation>("normalisation"),
+                list("gains", ParamKind::number(0.0, 2.0)),
+                list("pans", ParamKind::number(-1.0, 1.0)),
+                list("mutes", ParamKind::Bool),
+                list("solos", ParamKind::Bool),
+            ],
+            |_| Box::new(Mixer::default()),
+        );
+        registry.register("delay", vec![integer("samples", 0.0, 192_000.0)], |_| Box::new(DelayLine::new(0)));
+        registry.register(
+            "limiter",
+            vec![number("lookahead", 0.0, 20.0), number("release", 1.0, 1_000.0), number("ceiling", -24.0, 0.0)],
+            |sample_rate| Box::new(Limiter::new(sample_rate, 5.0, 50.0, -0.3)),
+        );
+        registry.register(
+            "soft_clip",
+            vec![choice::<ClipCurve>("curve"), number("drive", 0.0, 10.0)],
+            |_| Box::new(SoftClip::new(ClipCurve::Tanh, 1.0)),
+        );
+        registry.register("compressor", dynamics(), |sample_rate| Box::new(Dynamics::compressor(sample_rate)));
+        registry.register("expander", dynamics(), |sample_rate| Box::new(Dynamics::expander(sample_rate)));
+        registry.register("gate", dynamics(), |sample_rate| Box::new(Dynamics::gate(sample_rate)));
+        registry.register(
+            "reverb",
+            vec![
+                number("room_size", 0.0, 1.0),
+                number("damping", 0.0, 1.0),
+                number("pre_delay", 0.0, 500.0),
+                number("width", 0.0, 1.0),
+                number("wet", 0.0, 1.0),
+                number("dry", 0.0, 1.0),
+            ],
+            |sample_rate| Box::new(Reverb::new(sample_rate)),
+        );
+        registry.register(
+            "chorus",
+            vec![
+                integer("voices", 1.0, 8.0),
+                number("rate", 0.0, 10.0),
+                number("delay", 0.0, 60.0),
+                number("depth", 0.0, 60.0),
+                number("feedback", -0.95, 0.95),
+                number("mix", 0.0, 1.0),
+                number("stereo_phase", 0.0, 360.0),
+            ],
+            |sample_rate| Box::new(Chorus::new(sample_rate)),
+        );
+        registry.register(
+            "flanger",
+            vec![
+                number("rate", 0.0, 10.0),
+                number("delay", 0.0, 20.0),
+                number("depth", 0.0, 20.0),
+                number("feedback", -0.95, 0.95),
+                number("mix", 0.0, 1.0),
+                number("stereo_phase", 0.0, 360.0),
+                toggle("through_zero"),
+            ],
+            |sample_rate| Box::new(Flanger::new(sample_rate)),
+        );
+        registry.register(
+            "phaser",
+            vec![
+                integer("stages", 1.0, 12.0),
+                number("rate", 0.0, 10.0),
+                number("min", 20.0, 20_000.0),
+                number("max", 20.0, 20_000.0),
+                number("depth", 0.0, 1.0),
+                number("feedback", -0.95, 0.95),
+                number("mix", 0.0, 1.0),
+                number("stereo_phase", 0.0, 360.0),
+            ],
+            |sample_rate| Box::new(Phaser::new(sample_rate)),
+        );
+        registry.register(
+            "waveshaper",
+            vec![
+                ParamSpec::new(
+                    "shape",
+                    ParamKind::OneOf(vec![
+                        ParamKind::Choice(vec!["tanh", "diode", "foldback"]),
+                        ParamKind::list(ParamKind::number(-1.0, 1.0)),
+                    ]),
+                ),
+                choice::<Oversampling>("oversampling"),
+                number("drive", 0.0, 10.0),
+                number("output", 0.0, 2.0),
+                number("mix", 0.0, 1.0),
+            ],
+            |_| Box::new(Waveshaper::new(Shape::Tanh, Oversampling::None)),
+        );
+        registry.register(
+            "bitcrusher",
+            vec![number("bits", 1.0, 24.0), number("decimation", 1.0, 64.0)],
+            |_| Box::new(Bitcrusher::new(24.0, 1.0)),
+        );
+        registry.register(
+            "pan",
+            vec![number("pan", -1.0, 1.0), choice::<PanLaw>("pan_law")],
+            |_| Box::new(Pan::new(0.0, PanLaw::default())),
+        );
+        registry.register("balance", vec![number("balance", -1.0, 1.0)], |_| Box::new(Balance::new(0.0)));
+        registry.register("width", vec![number("width", 0.0, 2.0)], |_| Box::new(StereoWidth::new(1.0)));
+        registry.register("splitter", vec![integer("channel", 0.0, C.saturating_sub(1) as f64)], |_| Box::new(Splitter::new(0)));
+        registry.register("merger", Vec::new(), |_| Box::new(Merger::new()));
+        registry.register(
+            "router",
+            vec![list("gains", ParamKind::list(ParamKind::number(0.0, 1.0)))],
+            |_| Box::new(Router::identity()),
+        );
+        registry.register(
+            "noise",
+            vec![
+                choice::<NoiseColour>("colour"),
+                number("amplitude", 0.0, 1.0),
+                number("density", 1.0, 20_000.0),
+                seed(),
+            ],
+            |_| Box::new(Noise::new(NoiseColour::White, 0)),
+        );
+        registry.register(
+            "lfo",
+            vec![
+                choice::<LfoShape>("shape"),
+                number("hz", 0.0, 50.0),
+                number("beats", 0.0625, 16.0),
+                choice::<Polarity>("polarity"),
+                ParamSpec::new(
+                    "resolution",
+                    ParamKind::OneOf(vec![ParamKind::Choice(vec!["audio", "block"]), ParamKind::integer(1.0, 4_096.0)]),
+                ),
+                number("tempo", 20.0, 300.0),
+                number("amplitude", 0.0, 1.0),
+                number("start_phase", 0.0, 1.0),
+                seed(),
+            ],
+            |_| Box::new(Lfo::new(LfoShape::Sine, LfoRate::default())),
+        );
+        registry.register(
+            "sequencer",
+            vec![
+                integer("steps", 1.0, 64.0),
+                list("pitch", ParamKind::number(0.0, 127.0)),
+                list("velocity", ParamKind::number(0.0, 1.0)),
+                list("gate", ParamKind::number(0.0, 1.0)),
+                list("probability", ParamKind::number(0.0, 1.0)),
+                number("step_length", 0.0625, 4.0),
+                number("swing", 0.0, 0.9),
+                choice::<Direction>("direction"),
+                seed(),
+            ],
+            |_| Box::new(Sequencer::new(16)),
+        );
+        registry.register(
+            "clock",
+            vec![number("interval", 0.0625, 16.0), number("width", 0.0, 1.0)],
+            |_| Box::new(Clock::new(1.0)),
+        );
+        registry.register("clock_divider", vec![integer("divisor", 1.0, 64.0)], |_| Box::new(ClockDivider::new(2)));
+        registry.register("clock_multiplier", vec![integer("factor", 1.0, 64.0)], |_| Box::new(ClockMultiplier::new(2)));
+        registry.register(
+            "euclidean",
+            vec![integer("steps", 1.0, 64.0), integer("pulses", 0.0, 64.0), integer("rotation", 0.0, 63.0)],
+            |_| Box::new(Euclidean::new(16, 4, 0)),
+        );
+        registry.register("constant", vec![number("value", -1.0, 1.0)], |_| Box::new(Constant::new(0.0)));
+        registry.register("sum", vec![number("offset", -1.0, 1.0)], |_| Box::new(Sum::new(0.0)));
+        registry.register("multiply", vec![operand("a"), operand("b")], |_| {
+            Box::new(Multiply::new(Operand::Input(0), Operand::Input(1)))
+        });
+        registry.register("min", vec![operand("a"), operand("b")], |_| {
+            Box::new(Min::new(Operand::Input(0), Operand::Input(1)))
+        });
+        registry.register("max", vec![operand("a"), operand("b")], |_| {
+            Box::new(Max::new(Operand::Input(0), Operand::Input(1)))
+        });
+        registry.register("offset_scale", vec![operand("scale"), operand("offset")], |_| {
+            Box::new(OffsetScale::new(Operand::Value(1.0), Operand::Value(0.0)))
+        });
+        registry.register(
+            "range_map",
+            vec![
+                list("from", ParamKind::number(-1.0, 1.0)),
+                list("to", ParamKind::number(-1.0, 1.0)),
+                toggle("clamp"),
+            ],
+            |_| Box::new(RangeMap::new((-1.0, 1.0), (0.0, 1.0), false)),
+        );
+        registry.register("abs", Vec::new(), |_| Box::new(Abs::new()));
+        registry.register("sample_and_hold", Vec::new(), |_| Box::new(SampleAndHold::new()));
+        registry.register(
+            "slew",
+            vec![number("rise", 0.0, 1_000.0), number("fall", 0.0, 1_000.0)],
+            |_| Box::new(Slew::new(0.0, 0.0)),
+        );
+        registry.register(
+            "quantizer",
+            vec![integer("scale", 0.0, 4_095.0), integer("root", 0.0, 11.0)],
+            |_| Box::new(Quantizer::new(Scale::CHROMATIC, 0)),
+        );
+        // Each name in `params` becomes a setting of its own as well.
+        registry.register(
+            "expr",
+            vec![list("params", ParamKind::Text), ParamSpec::new("source", ParamKind::Text)],
+            |_| Box::new(Expr::new("in0", &[]).expect("a plain input compiles")),
+        );
+        // Feeds an input of the graph, for patches used as subgraphs.
+        registry.register("input", vec![integer("port", 0.0, 15.0)], |_| Box::new(Inlet::new(0)));
+        registry
+    }
+
+    /// Adds a node type, replacing any already registered under `name`.
+    /// The nodes `factory` builds should report `name` as their type
+    /// name so they can be saved. The defaults in `params` are read from
+    /// a node built by `factory`.
+    pub fn register(
+        &mut self,
+        name: &str,
+        mut params: Vec<ParamSpec>,
+        factory: impl Fn(u32) -> BoxedNode<N, C, S> + Send + Sync + 'static,
+    ) {
+        let defaults = factory(DEFAULT_SAMPLE_RATE).params();
+        for param in params.iter_mut() {
+            param.default = defaults.get(param.name).cloned();
+        }
+        self.types.insert(name.to_string(), NodeType { factory: Arc::new(factory), params });
+    }
+
+    /// Adds a node type that builds `template` as a
+    /// [`Subgraph`](crate::subgraph::Subgraph), so graphs containing it
+    /// can be saved and loaded like any other. The template is built
+    /// from the types registered so far, so register the templates it
+    /// uses first. Its settings are those of the nodes inside, named
+    /// `id.setting`, and aren't described in [`params`](Self::params).
+    pub fn register_template(&mut self, name: &'static str, template: SubgraphTemplate) -> Result<(), PatchError> {
+        let registry = self.clone();
+        template.instantiate(&registry, DEFAULT_SAMPLE_RATE)?;
+        self.register(name, Vec::new(), move |sample_rate| {
+            let mut subgraph = template
+                .instantiate(&registry, sample_rate)
+                .expect("the template built when it was registered");
+            subgraph.set_type_name(name);
+            Box::new(subgraph)
+        });
+        Ok(())
+    }
+
+    /// A new node of type `name` with its default settings.
+    pub fn create(&self, name: &str, sample_rate: u32) -> Option<BoxedNode<N, C, S>> {
+        self.types.get(name).map(|node_type| (node_type.factory)(sample_rate))
+    }
+
+    /// A new node of type `name` with `params` applied in order over its
+    /// defaults.
+    pub fn create_with(&self, name: &str, sample_rate: u32, params: &Params) -> Result<BoxedNode<N, C, S>, RegistryError> {
+        let mut node = self
+            .create(name, sample_rate)
+            .ok_or_else(|| RegistryError::UnknownType(name.to_string()))?;
+        for (param, value) in params.iter() {
+            node.set_param(param, value)
+                .map_err(|error| RegistryError::Param { param: param.to_string(), error })?;
+        }
+        Ok(node)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.types.contains_key(name)
+    }
+
+    /// The registered type names, in the order they were registered.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.types.keys().map(String::as_str)
+    }
+
+    /// The settings of node type `name`.
+    pub fn params(&self, name: &str) -> Option<&[ParamSpec]> {
+        self.types.get(name).map(|node_type| &node_type.params[..])
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Default for Registry<N, C, S> {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+fn number(name: &'static str, min: f64, max: f64) -> ParamSpec {
+    ParamSpec::new(name, ParamKind::number(min, max))
+}
+
+fn integer(name: &'static str, min: f64, max: f64) -> ParamSpec {
+    ParamSpec::new(name, ParamKind::integer(min, max))
+}
+
+fn toggle(name: &'static str) -> ParamSpec {
+    ParamSpec::new(name, ParamKind::Bool)
+}
+
+fn choice<T: Choice>(name: &'static str) -> ParamSpec {
+    ParamSpec::new(name, ParamKind::choice::<T>())
+}
+
+fn list(name: &'static str, kind: ParamKind) -> ParamSpec {
+    ParamSpec::new(name, ParamKind::list(kind))
+}
+
+fn seed() -> ParamSpec {
+    integer("seed", 0.0, u32::MAX as f64)
+}
+
+/// A fixed value, or `in0`, `in1`... for an input port.
+fn operand(name: &'static str) -> ParamSpec {
+    ParamSpec::new(name, ParamKind::OneOf(vec![ParamKind::number(-1.0, 1.0), ParamKind::Text]))
+}
+
+/// The settings shared by compressors, expanders and gates.
+fn dynamics() -> Vec<ParamSpec> {
+    vec![
+        number("threshold", -80.0, 0.0),
+        number("ratio", 1.0, 20.0),
+        number("knee", 0.0, 24.0),
+        number("makeup", 0.0, 24.0),
+        number("range", -80.0, 0.0),
+        number("attack", 0.1, 200.0),
+        number("release", 1.0, 2_000.0),
+        number("hold", 0.0, 500.0),
+        choice::<Detection>("detection"),
+        toggle("stereo_link"),
+    ]
+}
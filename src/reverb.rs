@@ -0,0 +1,239 @@
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::node::Node;
+use crate::param::{ParamError, Params, Value};
+use crate::sample::Sample;
+
+/// Comb and allpass lengths from Freeverb, tuned for 44.1 kHz.
+const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+/// How much longer the right channel's filters are, decorrelating the
+/// two sides.
+const STEREO_SPREAD: usize = 23;
+const TUNING_RATE: f32 = 44_100.0;
+const INPUT_GAIN: f32 = 0.015;
+/// Makes up for the quiet input gain, so a wet level of `1.0` is as loud
+/// as the dry signal.
+const WET_SCALE: f32 = 3.0;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+const MAX_PRE_DELAY_MS: f32 = 500.0;
+
+/// Values this small only cost CPU time as denormals, so they are
+/// flushed to zero as the tails decay.
+#[inline(always)]
+fn flush<S: Sample>(x: S) -> S {
+    if x.abs() < S::from_f32(1e-20) { S::ZERO } else { x }
+}
+
+/// A lowpass feedback comb filter.
+struct Comb<S: Sample> {
+    buffer: Vec<S>,
+    index: usize,
+    store: S,
+}
+
+impl<S: Sample> Comb<S> {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![S::ZERO; length.max(1)],
+            index: 0,
+            store: S::ZERO,
+        }
+    }
+    #[inline(always)]
+    fn tick(&mut self, input: S, feedback: S, damping: S) -> S {
+        let output = self.buffer[self.index];
+        self.store = flush(output * (S::ONE - damping) + self.store * damping);
+        self.buffer[self.index] = input + self.store * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+/// A Schroeder allpass, smearing the combs' output into a dense tail.
+struct Allpass<S: Sample> {
+    buffer: Vec<S>,
+    index: usize,
+}
+
+impl<S: Sample> Allpass<S> {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![S::ZERO; length.max(1)],
+            index: 0,
+        }
+    }
+    #[inline(always)]
+    fn tick(&mut self, input: S) -> S {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = flush(input + delayed * S::from_f32(ALLPASS_FEEDBACK));
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+}
+
+/// One side of the reverb, eight parallel combs into four allpasses.
+struct Tank<S: Sample> {
+    combs: Vec<Comb<S>>,
+    allpasses: Vec<Allpass<S>>,
+}
+
+impl<S: Sample> Tank<S> {
+    fn new(scale: f32, spread: usize) -> Self {
+        let length = |tuning: usize| ((tuning + spread) as f32 * scale) as usize;
+        Self {
+            combs: COMB_TUNINGS.iter().map(|&tuning| Comb::new(length(tuning))).collect(),
+            allpasses: ALLPASS_TUNINGS.iter().map(|&tuning| Allpass::new(length(tuning))).collect(),
+        }
+    }
+    #[inline(always)]
+    fn tick(&mut self, input: S, feedback: S, damping: S) -> S {
+        let mut output = S::ZERO;
+        for comb in self.combs.iter_mut() {
+            output += comb.tick(input, feedback, damping);
+        }
+        for allpass in self.allpasses.iter_mut() {
+            output = allpass.tick(output);
+        }
+        output
+    }
+}
+
+/// A stereo Schroeder-Moorer reverb, following Freeverb's topology. The
+/// input channels are summed into a pre-delay and then two banks of
+/// filters, one per side, whose outputs are crossed by the width.
+///
+/// Every buffer, including the longest possible pre-delay, is allocated
+/// on construction, so the reverb never allocates while processing.
+pub struct Reverb<const N: usize, S: Sample = f32> {
+    left: Tank<S>,
+    right: Tank<S>,
+    pre_delay: Vec<S>,
+    pre_delay_index: usize,
+    pre_delay_samples: usize,
+    pre_delay_ms: f32,
+    sample_rate: f32,
+    room_size: f32,
+    damping: f32,
+    width: f32,
+    wet: f32,
+    dry: f32,
+}
+
+impl<const N: usize, S: Sample> Reverb<N, S> {
+    pub fn new(sample_rate: u32) -> Self {
+        let sample_rate = sample_rate as f32;
+        let scale = sample_rate / TUNING_RATE;
+        Self {
+            left: Tank::new(scale, 0),
+            right: Tank::new(scale, STEREO_SPREAD),
+            pre_delay: vec![S::ZERO; (MAX_PRE_DELAY_MS * 0.001 * sample_rate) as usize + 1],
+            pre_delay_index: 0,
+            pre_delay_samples: 0,
+            pre_delay_ms: 0.0,
+            sample_rate,
+            room_size: 0.5,
+            damping: 0.5,
+            width: 1.0,
+            wet: 0.33,
+            dry: 1.0,
+        }
+    }
+    /// From `0.0` to `1.0`, how long the tail rings for.
+    pub fn set_room_size(&mut self, room_size: f32) {
+        self.room_size = room_size.clamp(0.0, 1.0);
+    }
+    /// From `0.0` to `1.0`, how quickly high frequencies die away.
+    pub fn set_damping(&mut self, damping: f32) {
+        self.damping = damping.clamp(0.0, 1.0);
+    }
+    /// The gap before the tail starts, up to half a second.
+    pub fn set_pre_delay(&mut self, ms: f32) {
+        self.pre_delay_ms = ms.clamp(0.0, MAX_PRE_DELAY_MS);
+        let samples = (self.pre_delay_ms * 0.001 * self.sample_rate) as usize;
+        self.pre_delay_samples = samples.min(self.pre_delay.len() - 1);
+    }
+    /// From `0.0`, a mono tail, to `1.0`, fully spread across both sides.
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width.clamp(0.0, 1.0);
+    }
+    pub fn set_wet(&mut self, wet: f32) {
+        self.wet = wet;
+    }
+    pub fn set_dry(&mut self, dry: f32) {
+        self.dry = dry;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Reverb<N, S> {
+    #[inline(always)]
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let input = &inputs[0];
+        let channels = C.min(2);
+        let feedback = S::from_f32(self.room_size * 0.28 + 0.7);
+        let damping = S::from_f32(self.damping * 0.4);
+        let wet = self.wet * WET_SCALE;
+        let wet_direct = S::from_f32(wet * (self.width * 0.5 + 0.5));
+        let wet_cross = S::from_f32(wet * (1.0 - self.width) * 0.5);
+        let dry = S::from_f32(self.dry);
+        let delay_len = self.pre_delay.len();
+
+        for n in 0..ctx.frames() {
+            let mut mono = S::ZERO;
+            for channel in &input[..channels] {
+                mono += channel[n];
+            }
+
+            self.pre_delay[self.pre_delay_index] = mono * S::from_f32(INPUT_GAIN);
+            let read = (self.pre_delay_index + delay_len - self.pre_delay_samples) % delay_len;
+            let delayed = self.pre_delay[read];
+            self.pre_delay_index = (self.pre_delay_index + 1) % delay_len;
+
+            let left = self.left.tick(delayed, feedback, damping);
+            let right = self.right.tick(delayed, feedback, damping);
+
+            match channels {
+                2 => {
+                    output[0][n] = left * wet_direct + right * wet_cross + input[0][n] * dry;
+                    output[1][n] = right * wet_direct + left * wet_cross + input[1][n] * dry;
+                }
+                _ => output[0][n] = (left + right) * (wet_direct + wet_cross) * S::from_f32(0.5) + input[0][n] * dry,
+            }
+        }
+    }
+
+    fn input_channels(&self, _port: usize) -> usize {
+        C.min(2)
+    }
+
+    fn output_channels(&self) -> usize {
+        C.min(2)
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("reverb")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("room_size", self.room_size)
+            .with("damping", self.damping)
+            .with("pre_delay", self.pre_delay_ms)
+            .with("width", self.width)
+            .with("wet", self.wet)
+            .with("dry", self.dry)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "room_size" => self.set_room_size(value.float()?),
+            "damping" => self.set_damping(value.float()?),
+            "pre_delay" => self.set_pre_delay(value.float()?),
+            "width" => self.set_width(value.float()?),
+            "wet" => self.set_wet(value.float()?),
+            "dry" => self.set_dry(value.float()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
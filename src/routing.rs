@@ -0,0 +1,157 @@
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::channel::ChannelMap;
+use crate::node::Node;
+use crate::param::{ParamError, Params, Value};
+use crate::sample::Sample;
+use crate::simd;
+
+/// Passes a single channel of its input on as a mono output, so that
+/// channel can be routed on its own. See
+/// [`DynamicAudioGraph::split`](crate::audio_graph::DynamicAudioGraph::split)
+/// to expose every channel of a node at once.
+pub struct Splitter<const N: usize> {
+    channel: usize,
+}
+
+impl<const N: usize> Splitter<N> {
+    pub fn new(channel: usize) -> Self {
+        Self { channel }
+    }
+    pub fn set_channel(&mut self, channel: usize) {
+        self.channel = channel;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Splitter<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let frames = ctx.frames();
+        let Some(out) = output.first_mut() else { return };
+        match inputs[0].get(self.channel) {
+            Some(input) => simd::copy(&mut out[..frames], input),
+            None => simd::fill(&mut out[..frames], S::ZERO),
+        }
+    }
+
+    fn output_channels(&self) -> usize {
+        1
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("splitter")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("channel", self.channel)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "channel" => self.set_channel(value.count()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Builds one output from mono inputs, the input on port `k` becoming
+/// channel `k`. Sources with more channels are summed to mono on the way
+/// in, and channels without an input are silent. See
+/// [`DynamicAudioGraph::merge`](crate::audio_graph::DynamicAudioGraph::merge).
+#[derive(Default)]
+pub struct Merger<const N: usize>;
+
+impl<const N: usize> Merger<N> {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Merger<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let frames = ctx.frames();
+        for (c, out) in output.iter_mut().enumerate() {
+            match inputs.get(c) {
+                Some(input) => simd::copy(&mut out[..frames], &input[0]),
+                None => simd::fill(&mut out[..frames], S::ZERO),
+            }
+        }
+    }
+
+    fn input_channels(&self, _port: usize) -> usize {
+        1
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("merger")
+    }
+}
+
+/// Remixes the channels of its input through a gain matrix, for swapping
+/// sides, folding down or any other routing within one signal.
+pub struct Router<const N: usize, const C: usize> {
+    gains: [[f32; C]; C],
+}
+
+impl<const N: usize, const C: usize> Router<N, C> {
+    /// `gains[output][input]` is how much of each input channel lands in
+    /// each output channel.
+    pub fn new(gains: [[f32; C]; C]) -> Self {
+        Self { gains }
+    }
+    /// Passes every channel through unchanged.
+    pub fn identity() -> Self {
+        let mut gains = [[0.0; C]; C];
+        for (c, row) in gains.iter_mut().enumerate() {
+            row[c] = 1.0;
+        }
+        Self { gains }
+    }
+    /// Reverses the order of the channels, which swaps left and right in
+    /// stereo.
+    pub fn reversed() -> Self {
+        let mut gains = [[0.0; C]; C];
+        for (c, row) in gains.iter_mut().enumerate() {
+            row[C - 1 - c] = 1.0;
+        }
+        Self { gains }
+    }
+    pub fn set_gain(&mut self, output: usize, input: usize, gain: f32) {
+        if output < C && input < C {
+            self.gains[output][input] = gain;
+        }
+    }
+    pub fn gains(&self) -> &[[f32; C]; C] {
+        &self.gains
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Router<N, C> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        ChannelMap::Matrix(self.gains).apply(&inputs[0], C, output, C, ctx.frames());
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("router")
+    }
+
+    /// The gains are saved as one list per output channel.
+    fn params(&self) -> Params {
+        let rows = self.gains.iter().map(|row| Value::from_floats(row.iter().copied()));
+        Params::new().with("gains", Value::List(rows.collect()))
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "gains" => {
+                for (output, row) in value.list()?.iter().enumerate() {
+                    for (input, gain) in row.floats()?.into_iter().enumerate() {
+                        self.set_gain(output, input, gain);
+                    }
+                }
+            }
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,531 @@
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::node::Node;
+use crate::noise::Rng;
+use crate::param::{Choice, ParamError, Params, Value};
+use crate::sample::Sample;
+
+/// Spots a clock or gate signal rising above zero.
+#[derive(Default)]
+pub(crate) struct Edge {
+    level: f32,
+}
+
+impl Edge {
+    #[inline(always)]
+    pub(crate) fn rising(&mut self, level: f32) -> bool {
+        let rising = level > 0.0 && self.level <= 0.0;
+        self.level = level;
+        rising
+    }
+}
+
+/// One step of a [`Sequencer`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    /// Sent on the pitch channel, in whatever unit the nodes it drives
+    /// expect, such as MIDI note numbers.
+    pub pitch: f32,
+    /// The height of the gate.
+    pub velocity: f32,
+    /// How much of the step the gate stays open for, from `0.0`, a rest,
+    /// to `1.0`, tied into the next step.
+    pub gate: f32,
+    /// From `0.0` to `1.0`, the chance the step plays each time it comes
+    /// round.
+    pub probability: f32,
+}
+
+impl Default for Step {
+    fn default() -> Self {
+        Self {
+            pitch: 60.0,
+            velocity: 1.0,
+            gate: 0.5,
+            probability: 1.0,
+        }
+    }
+}
+
+/// The order a [`Sequencer`] plays its steps in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Direction {
+    #[default]
+    Forward,
+    Backward,
+    /// Forward then backward, without repeating the end steps.
+    PingPong,
+    Random,
+}
+
+impl Choice for Direction {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("forward", Direction::Forward),
+        ("backward", Direction::Backward),
+        ("ping_pong", Direction::PingPong),
+        ("random", Direction::Random),
+    ];
+}
+
+/// A step sequencer clocked by the graph's transport. The first output
+/// channel carries the pitch of the current step, held between steps,
+/// and the second a gate at the step's velocity. While the transport is
+/// stopped the gate stays closed.
+pub struct Sequencer<const N: usize> {
+    steps: Vec<Step>,
+    step_beats: f64,
+    swing: f64,
+    direction: Direction,
+    seed: u32,
+    rng: Rng,
+    /// Steps counted from the start of the transport.
+    count: Option<u64>,
+    /// The step `count` landed on, and whether it won its probability
+    /// roll.
+    current: usize,
+    playing: bool,
+}
+
+impl<const N: usize> Sequencer<N> {
+    /// A sequencer with `steps` default steps, each a sixteenth note.
+    pub fn new(steps: usize) -> Self {
+        Self {
+            steps: vec![Step::default(); steps.max(1)],
+            step_beats: 0.25,
+            swing: 0.0,
+            direction: Direction::Forward,
+            seed: 0,
+            rng: Rng::default(),
+            count: None,
+            current: 0,
+            playing: false,
+        }
+    }
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+    /// # Panics
+    /// If `index` is out of range.
+    pub fn step_mut(&mut self, index: usize) -> &mut Step {
+        &mut self.steps[index]
+    }
+    pub fn set_step(&mut self, index: usize, step: Step) {
+        if let Some(slot) = self.steps.get_mut(index) {
+            *slot = step;
+        }
+    }
+    /// The length of a step in beats.
+    pub fn set_step_length(&mut self, beats: f64) {
+        self.step_beats = beats.max(1e-3);
+    }
+    /// From `0.0`, straight, towards `1.0`, delaying every second step
+    /// by that fraction of a step. A third gives a triplet shuffle.
+    pub fn set_swing(&mut self, swing: f64) {
+        self.swing = swing.clamp(0.0, 0.9);
+    }
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+    }
+    /// Seeds the probability rolls and random direction.
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+        self.rng = Rng::new(seed.into());
+    }
+
+    /// The step count at `beat`, and how far through that step it is.
+    #[inline(always)]
+    fn locate(&self, beat: f64) -> (u64, f64) {
+        let pair_beats = self.step_beats * 2.0;
+        let pair = (beat / pair_beats).floor();
+        let position = beat - pair * pair_beats;
+        let first = self.step_beats * (1.0 + self.swing);
+        match position < first {
+            true => (pair as u64 * 2, position / first),
+            false => (pair as u64 * 2 + 1, (position - first) / (pair_beats - first)),
+        }
+    }
+
+    #[inline(always)]
+    fn step_for(&mut self, count: u64) -> usize {
+        let len = self.steps.len() as u64;
+        (match self.direction {
+            Direction::Forward => count % len,
+            Direction::Backward => len - 1 - count % len,
+            Direction::PingPong if len > 1 => {
+                let position = count % (len * 2 - 2);
+                if position < len { position } else { len * 2 - 2 - position }
+            }
+            Direction::PingPong => 0,
+            Direction::Random => self.rng.next_u32() as u64 % len,
+        }) as usize
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Sequencer<N> {
+    fn process(&mut self, ctx: &AudioContext, _: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let transport = ctx.transport();
+        for n in 0..ctx.frames() {
+            let mut gate = 0.0;
+            if transport.is_playing() {
+                let (count, through) = self.locate(transport.beat_at(n));
+                if self.count != Some(count) {
+                    self.count = Some(count);
+                    self.current = self.step_for(count);
+                    self.playing = self.rng.next_f32() < self.steps[self.current].probability;
+                }
+                let step = self.steps[self.current];
+                if self.playing && through < step.gate as f64 {
+                    gate = step.velocity;
+                }
+            } else {
+                self.count = None;
+            }
+            output[0][n] = S::from_f32(self.steps[self.current].pitch);
+            if let Some(out) = output.get_mut(1) {
+                out[n] = S::from_f32(gate);
+            }
+        }
+    }
+
+    fn output_channels(&self) -> usize {
+        C.min(2)
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("sequencer")
+    }
+
+    /// Each field of the steps is saved as a list, one value per step.
+    fn params(&self) -> Params {
+        let field = |get: fn(&Step) -> f32| Value::from_floats(self.steps.iter().map(get));
+        Params::new()
+            .with("steps", self.steps.len())
+            .with("pitch", field(|step| step.pitch))
+            .with("velocity", field(|step| step.velocity))
+            .with("gate", field(|step| step.gate))
+            .with("probability", field(|step| step.probability))
+            .with("step_length", self.step_beats)
+            .with("swing", self.swing)
+            .with("direction", Value::from_choice(self.direction))
+            .with("seed", self.seed)
+    }
+
+    /// Changing `steps` keeps the existing steps and adds default ones.
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        let field = |steps: &mut [Step], set: fn(&mut Step, f32)| -> Result<(), ParamError> {
+            steps.iter_mut().zip(value.floats()?).for_each(|(step, value)| set(step, value));
+            Ok(())
+        };
+        match name {
+            "steps" => {
+                self.steps.resize(value.count()?.max(1), Step::default());
+                self.current = self.current.min(self.steps.len() - 1);
+            }
+            "pitch" => field(&mut self.steps, |step, value| step.pitch = value)?,
+            "velocity" => field(&mut self.steps, |step, value| step.velocity = value)?,
+            "gate" => field(&mut self.steps, |step, value| step.gate = value)?,
+            "probability" => field(&mut self.steps, |step, value| step.probability = value)?,
+            "step_length" => self.set_step_length(value.number()?),
+            "swing" => self.set_swing(value.number()?),
+            "direction" => self.set_direction(value.choice()?),
+            "seed" => self.set_seed(u32::try_from(value.count()?).map_err(|_| ParamError::Invalid)?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Sends a pulse every so many beats of the graph's transport, to drive
+/// dividers, Euclidean patterns and anything else listening for a clock.
+pub struct Clock<const N: usize> {
+    beats: f64,
+    width: f64,
+}
+
+impl<const N: usize> Clock<N> {
+    /// Pulses every `beats`, each open for half the interval.
+    pub fn new(beats: f64) -> Self {
+        Self {
+            beats: beats.max(1e-3),
+            width: 0.5,
+        }
+    }
+    pub fn set_interval(&mut self, beats: f64) {
+        self.beats = beats.max(1e-3);
+    }
+    /// How much of each interval the pulse is open for.
+    pub fn set_width(&mut self, width: f64) {
+        self.width = width.clamp(0.0, 1.0);
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Clock<N> {
+    fn process(&mut self, ctx: &AudioContext, _: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let transport = ctx.transport();
+        let Some(out) = output.first_mut() else { return };
+        for (n, sample) in out[..ctx.frames()].iter_mut().enumerate() {
+            let through = (transport.beat_at(n) / self.beats).fract();
+            let open = transport.is_playing() && through < self.width;
+            *sample = if open { S::ONE } else { S::ZERO };
+        }
+    }
+
+    fn output_channels(&self) -> usize {
+        1
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("clock")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("interval", self.beats).with("width", self.width)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "interval" => self.set_interval(value.number()?),
+            "width" => self.set_width(value.number()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Passes every `divisor`th pulse of the clock on its first input,
+/// starting with the first.
+pub struct ClockDivider<const N: usize> {
+    divisor: u32,
+    count: u32,
+    edge: Edge,
+    open: bool,
+}
+
+impl<const N: usize> ClockDivider<N> {
+    pub fn new(divisor: u32) -> Self {
+        Self {
+            divisor: divisor.max(1),
+            count: 0,
+            edge: Edge::default(),
+            open: false,
+        }
+    }
+    pub fn set_divisor(&mut self, divisor: u32) {
+        self.divisor = divisor.max(1);
+    }
+    /// Makes the next pulse the first of a group.
+    pub fn reset(&mut self) {
+        self.count = 0;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for ClockDivider<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let Some(out) = output.first_mut() else { return };
+        let Some(clock) = inputs.first() else {
+            out[..ctx.frames()].fill(S::ZERO);
+            return;
+        };
+        for n in 0..ctx.frames() {
+            let level = clock[0][n].to_f32();
+            if self.edge.rising(level) {
+                self.open = self.count == 0;
+                self.count = (self.count + 1) % self.divisor;
+            }
+            out[n] = if self.open && level > 0.0 { S::ONE } else { S::ZERO };
+        }
+    }
+
+    fn input_channels(&self, _port: usize) -> usize {
+        1
+    }
+
+    fn output_channels(&self) -> usize {
+        1
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("clock_divider")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("divisor", self.divisor)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "divisor" => self.set_divisor(value.count()? as u32),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Sends `factor` evenly spaced pulses for every pulse of the clock on
+/// its first input, timed from the gap between the last two pulses.
+pub struct ClockMultiplier<const N: usize> {
+    factor: u32,
+    edge: Edge,
+    /// Samples since the last input pulse, once there has been one, and
+    /// the gap before it.
+    elapsed: Option<u64>,
+    period: u64,
+}
+
+impl<const N: usize> ClockMultiplier<N> {
+    pub fn new(factor: u32) -> Self {
+        Self {
+            factor: factor.max(1),
+            edge: Edge::default(),
+            elapsed: None,
+            period: 0,
+        }
+    }
+    pub fn set_factor(&mut self, factor: u32) {
+        self.factor = factor.max(1);
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for ClockMultiplier<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let Some(out) = output.first_mut() else { return };
+        let Some(clock) = inputs.first() else {
+            out[..ctx.frames()].fill(S::ZERO);
+            return;
+        };
+        for n in 0..ctx.frames() {
+            if self.edge.rising(clock[0][n].to_f32()) {
+                if let Some(elapsed) = self.elapsed {
+                    self.period = elapsed;
+                }
+                self.elapsed = Some(0);
+            }
+            // Until two pulses have arrived there is no tempo to follow.
+            let open = match (self.elapsed, self.period) {
+                (Some(elapsed), period) if period > 0 => {
+                    let through = (elapsed * self.factor as u64 % period) as f64 / period as f64;
+                    elapsed < period && through < 0.5
+                }
+                _ => false,
+            };
+            out[n] = if open { S::ONE } else { S::ZERO };
+            if let Some(elapsed) = &mut self.elapsed {
+                *elapsed += 1;
+            }
+        }
+    }
+
+    fn input_channels(&self, _port: usize) -> usize {
+        1
+    }
+
+    fn output_channels(&self) -> usize {
+        1
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("clock_multiplier")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("factor", self.factor)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "factor" => self.set_factor(value.count()? as u32),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Spreads `pulses` hits as evenly as possible over `steps` steps,
+/// advancing one step per pulse of the clock on its first input and
+/// passing the clock through on hits.
+pub struct Euclidean<const N: usize> {
+    steps: u32,
+    pulses: u32,
+    rotation: u32,
+    position: u32,
+    edge: Edge,
+    open: bool,
+}
+
+impl<const N: usize> Euclidean<N> {
+    pub fn new(steps: u32, pulses: u32, rotation: u32) -> Self {
+        let steps = steps.max(1);
+        Self {
+            steps,
+            pulses: pulses.min(steps),
+            rotation: rotation % steps,
+            position: 0,
+            edge: Edge::default(),
+            open: false,
+        }
+    }
+    pub fn set_pattern(&mut self, steps: u32, pulses: u32, rotation: u32) {
+        *self = Self {
+            position: self.position,
+            ..Self::new(steps, pulses, rotation)
+        };
+        self.position %= self.steps;
+    }
+    /// Whether `step` of the pattern is a hit.
+    pub fn is_hit(&self, step: u32) -> bool {
+        let step = (step + self.rotation) % self.steps;
+        (step * self.pulses) % self.steps < self.pulses
+    }
+    /// Makes the next pulse the first step of the pattern.
+    pub fn reset(&mut self) {
+        self.position = 0;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Euclidean<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let Some(out) = output.first_mut() else { return };
+        let Some(clock) = inputs.first() else {
+            out[..ctx.frames()].fill(S::ZERO);
+            return;
+        };
+        for n in 0..ctx.frames() {
+            let level = clock[0][n].to_f32();
+            if self.edge.rising(level) {
+                self.open = self.is_hit(self.position);
+                self.position = (self.position + 1) % self.steps;
+            }
+            out[n] = if self.open && level > 0.0 { S::ONE } else { S::ZERO };
+        }
+    }
+
+    fn input_channels(&self, _port: usize) -> usize {
+        1
+    }
+
+    fn output_channels(&self) -> usize {
+        1
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("euclidean")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("steps", self.steps)
+            .with("pulses", self.pulses)
+            .with("rotation", self.rotation)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        let value = value.count()? as u32;
+        match name {
+            "steps" => self.set_pattern(value, self.pulses, self.rotation),
+            "pulses" => self.set_pattern(self.steps, value, self.rotation),
+            "rotation" => self.set_pattern(self.steps, self.pulses, value),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use core::ops::{Add, Mul, Sub};
+
+use crate::sample::Sample;
+
+/// The number of samples processed together. Eight `f32`s fill an AVX
+/// register on x86_64, or a pair of NEON registers on aarch64.
+pub const LANES: usize = 8;
+
+/// A fixed width group of samples. Operations are written lane by lane
+/// over a plain array, which the compiler lowers to vector instructions
+/// on every target that has them, without needing nightly `std::simd`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C, align(32))]
+pub struct Lanes<S: Sample>([S; LANES]);
+
+pub type F32x8 = Lanes<f32>;
+pub type F64x8 = Lanes<f64>;
+
+impl<S: Sample> Lanes<S> {
+    #[inline(always)]
+    pub fn splat(value: S) -> Self {
+        Self([value; LANES])
+    }
+
+    /// Loads the first `LANES` samples of `src`.
+    #[inline(always)]
+    pub fn load(src: &[S]) -> Self {
+        let mut lanes = [S::ZERO; LANES];
+        lanes.copy_from_slice(&src[..LANES]);
+        Self(lanes)
+    }
+
+    /// Stores the lanes into the first `LANES` samples of `dst`.
+    #[inline(always)]
+    pub fn store(self, dst: &mut [S]) {
+        dst[..LANES].copy_from_slice(&self.0);
+    }
+
+    #[inline(always)]
+    pub fn mul_add(self, a: Self, b: Self) -> Self {
+        let mut lanes = self.0;
+        for ((lane, a), b) in lanes.iter_mut().zip(a.0).zip(b.0) {
+            *lane += a * b;
+        }
+        Self(lanes)
+    }
+
+    #[inline(always)]
+    pub fn clamp(self, min: Self, max: Self) -> Self {
+        let mut lanes = self.0;
+        for ((lane, min), max) in lanes.iter_mut().zip(min.0).zip(max.0) {
+            *lane = lane.max(min).min(max);
+        }
+        Self(lanes)
+    }
+}
+
+impl<S: Sample> Add for Lanes<S> {
+    type Output = Self;
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        let mut lanes = self.0;
+        for (lane, rhs) in lanes.iter_mut().zip(rhs.0) {
+            *lane += rhs;
+        }
+        Self(lanes)
+    }
+}
+
+impl<S: Sample> Sub for Lanes<S> {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        let mut lanes = self.0;
+        for (lane, rhs) in lanes.iter_mut().zip(rhs.0) {
+            *lane -= rhs;
+        }
+        Self(lanes)
+    }
+}
+
+impl<S: Sample> Mul for Lanes<S> {
+    type Output = Self;
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        let mut lanes = self.0;
+        for (lane, rhs) in lanes.iter_mut().zip(rhs.0) {
+            *lane *= rhs;
+        }
+        Self(lanes)
+    }
+}
+
+/// Applies `lanes` to every full group of `LANES` samples and `scalar` to
+/// whatever is left over, so callers never need to care about the tail.
+#[inline(always)]
+fn zip_apply<S: Sample>(
+    dst: &mut [S],
+    src: &[S],
+    lanes: impl Fn(Lanes<S>, Lanes<S>) -> Lanes<S>,
+    scalar: impl Fn(S, S) -> S,
+) {
+    let len = dst.len().min(src.len());
+    let (dst, src) = (&mut dst[..len], &src[..len]);
+    let mut dst_chunks = dst.chunks_exact_mut(LANES);
+    let mut src_chunks = src.chunks_exact(LANES);
+    for (d, s) in (&mut dst_chunks).zip(&mut src_chunks) {
+        lanes(Lanes::load(d), Lanes::load(s)).store(d);
+    }
+    for (d, s) in dst_chunks.into_remainder().iter_mut().zip(src_chunks.remainder()) {
+        *d = scalar(*d, *s);
+    }
+}
+
+#[inline(always)]
+fn map<S: Sample>(dst: &mut [S], lanes: impl Fn(Lanes<S>) -> Lanes<S>, scalar: impl Fn(S) -> S) {
+    let mut chunks = dst.chunks_exact_mut(LANES);
+    for d in &mut chunks {
+        lanes(Lanes::load(d)).store(d);
+    }
+    for d in chunks.into_remainder() {
+        *d = scalar(*d);
+    }
+}
+
+/// `dst[i] += src[i]`
+#[inline(always)]
+pub fn add<S: Sample>(dst: &mut [S], src: &[S]) {
+    zip_apply(dst, src, |d, s| d + s, |d, s| d + s);
+}
+
+/// `dst[i] -= src[i]`
+#[inline(always)]
+pub fn sub<S: Sample>(dst: &mut [S], src: &[S]) {
+    zip_apply(dst, src, |d, s| d - s, |d, s| d - s);
+}
+
+/// `dst[i] *= src[i]`
+#[inline(always)]
+pub fn mul<S: Sample>(dst: &mut [S], src: &[S]) {
+    zip_apply(dst, src, |d, s| d * s, |d, s| d * s);
+}
+
+/// `dst[i] *= scalar`
+#[inline(always)]
+pub fn scale<S: Sample>(dst: &mut [S], scalar: S) {
+    let k = Lanes::splat(scalar);
+    map(dst, |d| d * k, |d| d * scalar);
+}
+
+/// `dst[i] += src[i] * scalar`
+#[inline(always)]
+pub fn mul_add<S: Sample>(dst: &mut [S], src: &[S], scalar: S) {
+    let k = Lanes::splat(scalar);
+    zip_apply(dst, src, |d, s| d.mul_add(s, k), |d, s| d + s * scalar);
+}
+
+/// Limits every sample to `min..=max`.
+#[inline(always)]
+pub fn clamp<S: Sample>(dst: &mut [S], min: S, max: S) {
+    let (lo, hi) = (Lanes::splat(min), Lanes::splat(max));
+    map(dst, |d| d.clamp(lo, hi), |d| d.max(min).min(max));
+}
+
+/// `dst[i] = src[i]`
+#[inline(always)]
+pub fn copy<S: Sample>(dst: &mut [S], src: &[S]) {
+    let len = dst.len().min(src.len());
+    dst[..len].copy_from_slice(&src[..len]);
+}
+
+/// `dst[i] = value`
+#[inline(always)]
+pub fn fill<S: Sample>(dst: &mut [S], value: S) {
+    dst.fill(value);
+}
@@ -0,0 +1,193 @@
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::mixer::PanLaw;
+use crate::node::Node;
+use crate::param::{ParamError, Params, Value};
+use crate::sample::Sample;
+use crate::simd;
+
+/// Positions a mono source between the left and right channels. Stereo
+/// sources are summed to mono on the way in; use [`Balance`] to move
+/// them without losing their image. In a mono graph the input passes
+/// through.
+#[derive(Default)]
+pub struct Pan<const N: usize> {
+    pan: f32,
+    pan_law: PanLaw,
+}
+
+impl<const N: usize> Pan<N> {
+    /// `pan` runs from `-1.0` (left) to `1.0` (right).
+    pub fn new(pan: f32, pan_law: PanLaw) -> Self {
+        Self { pan, pan_law }
+    }
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan = pan;
+    }
+    pub fn set_pan_law(&mut self, pan_law: PanLaw) {
+        self.pan_law = pan_law;
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Pan<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let frames = ctx.frames();
+        let input = &inputs[0][0][..frames];
+        match &mut output[..] {
+            [left, right, ..] => {
+                let (l, r) = self.pan_law.gains(self.pan);
+                simd::copy(&mut left[..frames], input);
+                simd::scale(&mut left[..frames], S::from_f32(l));
+                simd::copy(&mut right[..frames], input);
+                simd::scale(&mut right[..frames], S::from_f32(r));
+            }
+            [mono] => simd::copy(&mut mono[..frames], input),
+            [] => {}
+        }
+    }
+
+    fn input_channels(&self, _port: usize) -> usize {
+        1
+    }
+
+    fn output_channels(&self) -> usize {
+        C.min(2)
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("pan")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("pan", self.pan)
+            .with("pan_law", Value::from_choice(self.pan_law))
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "pan" => self.set_pan(value.float()?),
+            "pan_law" => self.set_pan_law(value.choice()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Turns down one side of a stereo signal, leaving the other at unity.
+/// Unlike [`Pan`], each side keeps its own content.
+#[derive(Default)]
+pub struct Balance<const N: usize> {
+    balance: f32,
+}
+
+impl<const N: usize> Balance<N> {
+    /// `balance` runs from `-1.0`, only the left side, to `1.0`, only the
+    /// right.
+    pub fn new(balance: f32) -> Self {
+        Self { balance: balance.clamp(-1.0, 1.0) }
+    }
+    pub fn set_balance(&mut self, balance: f32) {
+        self.balance = balance.clamp(-1.0, 1.0);
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Balance<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let frames = ctx.frames();
+        let gains = match C {
+            1 => [1.0, 1.0],
+            _ => [(1.0 - self.balance).min(1.0), (1.0 + self.balance).min(1.0)],
+        };
+        for ((out, input), gain) in output.iter_mut().zip(&inputs[0]).zip(gains) {
+            simd::copy(&mut out[..frames], input);
+            simd::scale(&mut out[..frames], S::from_f32(gain));
+        }
+    }
+
+    fn input_channels(&self, _port: usize) -> usize {
+        C.min(2)
+    }
+
+    fn output_channels(&self) -> usize {
+        C.min(2)
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("balance")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("balance", self.balance)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "balance" => self.set_balance(value.float()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Narrows or widens a stereo signal by scaling its side (`L - R`)
+/// against its mid (`L + R`).
+pub struct StereoWidth<const N: usize> {
+    width: f32,
+}
+
+impl<const N: usize> StereoWidth<N> {
+    /// `0.0` collapses to mono, `1.0` leaves the signal unchanged and
+    /// anything above exaggerates the difference between the sides.
+    pub fn new(width: f32) -> Self {
+        Self { width: width.max(0.0) }
+    }
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width.max(0.0);
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for StereoWidth<N> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let frames = ctx.frames();
+        let input = &inputs[0];
+        let half = S::from_f32(0.5);
+        let width = S::from_f32(self.width);
+        match &mut output[..] {
+            [left, right, ..] => {
+                for n in 0..frames {
+                    let mid = (input[0][n] + input[1][n]) * half;
+                    let side = (input[0][n] - input[1][n]) * half * width;
+                    left[n] = mid + side;
+                    right[n] = mid - side;
+                }
+            }
+            [mono] => simd::copy(&mut mono[..frames], &input[0]),
+            [] => {}
+        }
+    }
+
+    fn input_channels(&self, _port: usize) -> usize {
+        C.min(2)
+    }
+
+    fn output_channels(&self) -> usize {
+        C.min(2)
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("width")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("width", self.width)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "width" => self.set_width(value.float()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
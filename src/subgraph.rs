@@ -0,0 +1,161 @@
+use crate::audio_context::AudioContext;
+use crate::audio_graph::DynamicAudioGraph;
+use crate::buffer::Frame;
+use crate::node::Node;
+use crate::param::{ParamError, Params, Value};
+use crate::patch::{Patch, PatchError};
+use crate::registry::Registry;
+use crate::sample::Sample;
+use crate::simd;
+
+/// Stands in for one input of a graph. The graph writes the input
+/// straight into the inlet's output before computing a block, so nodes
+/// connected to it read the input like any other source. See
+/// [`DynamicAudioGraph::add_input`].
+pub struct Inlet<const N: usize> {
+    port: usize,
+}
+
+impl<const N: usize> Inlet<N> {
+    pub fn new(port: usize) -> Self {
+        Self { port }
+    }
+    /// The input of the graph this inlet stands in for.
+    pub fn port(&self) -> usize {
+        self.port
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Inlet<N> {
+    /// Leaves the input the graph wrote in place.
+    fn process(&mut self, _: &AudioContext, _: &[Frame<N, C, S>], _: &mut Frame<N, C, S>) {}
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("input")
+    }
+
+    fn params(&self) -> Params {
+        Params::new().with("port", self.port)
+    }
+
+    /// Only takes effect when the graph is built from a patch, which
+    /// feeds each inlet the input of its port.
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "port" => self.port = value.count()?,
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// A whole graph used as a single node, for wrapping up a reusable chain
+/// such as a synth voice or a channel strip. The inputs of the node feed
+/// the inner graph's [inputs](DynamicAudioGraph::add_input) in port
+/// order, and its output is the inner graph's sink.
+///
+/// The inner graph follows the outer graph's sample rate, block size and
+/// transport, and can contain subgraphs of its own.
+///
+/// Subgraphs built by a template registered with
+/// [`Registry::register_template`] report the template's name as their
+/// type, so graphs containing them can be saved. Others can't.
+pub struct Subgraph<const N: usize, const C: usize, S: Sample = f32> {
+    graph: DynamicAudioGraph<N, C, S>,
+    type_name: Option<&'static str>,
+}
+
+impl<const N: usize, const C: usize, S: Sample> Subgraph<N, C, S> {
+    /// Wraps `graph`, which should be complete: the channels the node
+    /// writes are read from its sink when the node is added to the outer
+    /// graph.
+    pub fn new(graph: DynamicAudioGraph<N, C, S>) -> Self {
+        Self { graph, type_name: None }
+    }
+    /// The type name the subgraph is saved as, which should be
+    /// registered as a template that builds the same graph.
+    pub fn set_type_name(&mut self, type_name: &'static str) {
+        self.type_name = Some(type_name);
+    }
+    pub fn graph(&self) -> &DynamicAudioGraph<N, C, S> {
+        &self.graph
+    }
+    /// The inner graph, for changing settings of the nodes inside.
+    pub fn graph_mut(&mut self) -> &mut DynamicAudioGraph<N, C, S> {
+        &mut self.graph
+    }
+    pub fn into_graph(self) -> DynamicAudioGraph<N, C, S> {
+        self.graph
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Subgraph<N, C, S> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let frames = ctx.frames();
+        let sink = self.graph.next_block_nested(ctx, inputs);
+        for (out, sink) in output.iter_mut().zip(sink) {
+            simd::copy(&mut out[..frames], &sink[..frames]);
+        }
+    }
+
+    fn output_channels(&self) -> usize {
+        match self.graph.node_count() {
+            0 => C,
+            _ => self.graph.output_channels(self.graph.sink_index()),
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        self.type_name
+    }
+
+    /// The settings of every named node inside, as `id.setting`.
+    fn params(&self) -> Params {
+        let mut params = Params::new();
+        for index in 0..self.graph.node_count() {
+            let Some(id) = self.graph.node_name(index) else { continue };
+            for (name, value) in self.graph.node(index).params().iter() {
+                params.push(&format!("{id}.{name}"), value.clone());
+            }
+        }
+        params
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        // Node ids may contain dots themselves, so try each split.
+        for (at, _) in name.match_indices('.') {
+            if let Some(index) = self.graph.node_index(&name[..at]) {
+                return self.graph.node_mut(index).set_param(&name[at + 1..], value);
+            }
+        }
+        Err(ParamError::Unknown)
+    }
+}
+
+/// A patch that can be built into any number of independent
+/// [`Subgraph`]s, such as one per voice of a synth. Nodes of type
+/// `input` in the patch become the subgraph's inputs, fed by the port in
+/// their `port` setting.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubgraphTemplate {
+    patch: Patch,
+}
+
+impl SubgraphTemplate {
+    pub fn new(patch: Patch) -> Self {
+        Self { patch }
+    }
+    pub fn patch(&self) -> &Patch {
+        &self.patch
+    }
+    /// Builds a new subgraph from the template, with its nodes made for
+    /// the outer graph's `sample_rate`.
+    pub fn instantiate<const N: usize, const C: usize, S: Sample>(
+        &self,
+        registry: &Registry<N, C, S>,
+        sample_rate: u32,
+    ) -> Result<Subgraph<N, C, S>, PatchError> {
+        let patch = Patch { sample_rate, ..self.patch.clone() };
+        Ok(Subgraph::new(patch.build(registry)?))
+    }
+}
@@ -0,0 +1,242 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::atomic::AtomicF32;
+
+/// The settings a [`TransportHandle`] changes from outside the audio
+/// thread, picked up by the graph at the start of every block.
+#[derive(Debug)]
+struct Shared {
+    playing: AtomicBool,
+    tempo: AtomicF32,
+    numerator: AtomicU32,
+    denominator: AtomicU32,
+    looping: AtomicBool,
+    loop_start: AtomicF32,
+    loop_end: AtomicF32,
+    /// A beat position to jump to, as `f64` bits, taken when `seeking` is
+    /// set.
+    seek: AtomicU64,
+    seeking: AtomicBool,
+    /// Where the transport was after the last block, as `f64` bits.
+    beat_position: AtomicU64,
+    sample_position: AtomicU64,
+}
+
+/// Controls a graph's transport from any thread, such as a UI or MIDI
+/// controller. Every change lands at the start of the next block.
+#[derive(Clone, Debug)]
+pub struct TransportHandle {
+    shared: Arc<Shared>,
+}
+
+impl TransportHandle {
+    fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                playing: AtomicBool::new(false),
+                tempo: AtomicF32::new(120.0),
+                numerator: AtomicU32::new(4),
+                denominator: AtomicU32::new(4),
+                looping: AtomicBool::new(false),
+                loop_start: AtomicF32::new(0.0),
+                loop_end: AtomicF32::new(0.0),
+                seek: AtomicU64::new(0),
+                seeking: AtomicBool::new(false),
+                beat_position: AtomicU64::new(0f64.to_bits()),
+                sample_position: AtomicU64::new(0),
+            }),
+        }
+    }
+    pub fn play(&self) {
+        self.shared.playing.store(true, Ordering::Relaxed);
+    }
+    /// Pauses at the current position.
+    pub fn stop(&self) {
+        self.shared.playing.store(false, Ordering::Relaxed);
+    }
+    pub fn is_playing(&self) -> bool {
+        self.shared.playing.load(Ordering::Relaxed)
+    }
+    /// Beats per minute, where a beat is one count of the time
+    /// signature.
+    pub fn set_tempo(&self, bpm: f32) {
+        self.shared.tempo.store(bpm.max(1.0));
+    }
+    pub fn set_time_signature(&self, numerator: u32, denominator: u32) {
+        self.shared.numerator.store(numerator.max(1), Ordering::Relaxed);
+        self.shared.denominator.store(denominator.max(1), Ordering::Relaxed);
+    }
+    /// Loops between two beat positions while playing, or stops looping
+    /// with `None`.
+    pub fn set_loop(&self, range: Option<(f32, f32)>) {
+        match range {
+            Some((start, end)) if end > start => {
+                self.shared.loop_start.store(start.max(0.0));
+                self.shared.loop_end.store(end);
+                self.shared.looping.store(true, Ordering::Relaxed);
+            }
+            _ => self.shared.looping.store(false, Ordering::Relaxed),
+        }
+    }
+    /// Moves the transport to a beat position.
+    pub fn seek(&self, beat: f64) {
+        self.shared.seek.store(beat.max(0.0).to_bits(), Ordering::Relaxed);
+        self.shared.seeking.store(true, Ordering::Release);
+    }
+    /// The beat position after the last block.
+    pub fn beat_position(&self) -> f64 {
+        f64::from_bits(self.shared.beat_position.load(Ordering::Relaxed))
+    }
+    /// The sample position after the last block.
+    pub fn sample_position(&self) -> u64 {
+        self.shared.sample_position.load(Ordering::Relaxed)
+    }
+}
+
+/// The musical clock of a graph, as seen by nodes through
+/// [`AudioContext::transport`](crate::audio_context::AudioContext::transport).
+/// Positions are those at the first sample of the current block.
+#[derive(Clone, Debug)]
+pub struct Transport {
+    handle: TransportHandle,
+    sample_rate: f32,
+    playing: bool,
+    tempo: f32,
+    time_signature: (u32, u32),
+    loop_range: Option<(f64, f64)>,
+    beat_position: f64,
+    sample_position: u64,
+}
+
+impl Transport {
+    pub(crate) fn new(sample_rate: f32) -> Self {
+        Self {
+            handle: TransportHandle::new(),
+            sample_rate,
+            playing: false,
+            tempo: 120.0,
+            time_signature: (4, 4),
+            loop_range: None,
+            beat_position: 0.0,
+            sample_position: 0,
+        }
+    }
+
+    /// A handle for starting, stopping and moving the transport.
+    pub fn handle(&self) -> TransportHandle {
+        self.handle.clone()
+    }
+    #[inline(always)]
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+    #[inline(always)]
+    pub fn tempo(&self) -> f32 {
+        self.tempo
+    }
+    /// Beats per bar, and the note value of a beat.
+    #[inline(always)]
+    pub fn time_signature(&self) -> (u32, u32) {
+        self.time_signature
+    }
+    #[inline(always)]
+    pub fn loop_range(&self) -> Option<(f64, f64)> {
+        self.loop_range
+    }
+    /// Samples played, counted from where the transport was last moved
+    /// to at the tempo it had then. Loops don't move it back.
+    #[inline(always)]
+    pub fn sample_position(&self) -> u64 {
+        self.sample_position
+    }
+    #[inline(always)]
+    pub fn beat_position(&self) -> f64 {
+        self.beat_position
+    }
+    /// The bar, counting from zero, and the beat within it.
+    pub fn bar_position(&self) -> (u64, f64) {
+        let beats_per_bar = self.time_signature.0 as f64;
+        let bar = (self.beat_position / beats_per_bar).floor();
+        (bar as u64, self.beat_position - bar * beats_per_bar)
+    }
+    /// How far the transport moves in one sample while playing.
+    #[inline(always)]
+    pub fn beats_per_sample(&self) -> f64 {
+        self.tempo as f64 / 60.0 / self.sample_rate as f64
+    }
+    /// The length of `beats` at the current tempo, in samples, for
+    /// syncing delays and the like.
+    pub fn beats_to_samples(&self, beats: f64) -> f64 {
+        beats / self.beats_per_sample()
+    }
+    /// The beat position `offset` samples into the block, wrapped by the
+    /// loop. Stands still while stopped.
+    #[inline(always)]
+    pub fn beat_at(&self, offset: usize) -> f64 {
+        match self.playing {
+            true => self.wrap(self.beat_position + offset as f64 * self.beats_per_sample()),
+            false => self.beat_position,
+        }
+    }
+
+    #[inline(always)]
+    fn wrap(&self, beat: f64) -> f64 {
+        match self.loop_range {
+            Some((start, end)) if beat >= end => start + (beat - end) % (end - start),
+            _ => beat,
+        }
+    }
+
+    /// Takes any changes made through the handle.
+    pub(crate) fn sync(&mut self) {
+        let shared = &self.handle.shared;
+        self.playing = shared.playing.load(Ordering::Relaxed);
+        self.tempo = shared.tempo.load();
+        self.time_signature = (
+            shared.numerator.load(Ordering::Relaxed),
+            shared.denominator.load(Ordering::Relaxed),
+        );
+        self.loop_range = match shared.looping.load(Ordering::Relaxed) {
+            true => Some((shared.loop_start.load() as f64, shared.loop_end.load() as f64)),
+            false => None,
+        };
+        if shared.seeking.swap(false, Ordering::Acquire) {
+            self.beat_position = f64::from_bits(shared.seek.load(Ordering::Relaxed));
+            self.sample_position = self.beats_to_samples(self.beat_position) as u64;
+        }
+    }
+
+    /// Moves past a block of `frames` samples and publishes the new
+    /// position to the handle.
+    pub(crate) fn advance(&mut self, frames: usize) {
+        if self.playing {
+            self.beat_position = self.beat_at(frames);
+            self.sample_position += frames as u64;
+        }
+        let shared = &self.handle.shared;
+        shared.beat_position.store(self.beat_position.to_bits(), Ordering::Relaxed);
+        shared.sample_position.store(self.sample_position, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Takes the state of an outer graph's transport, and publishes its
+    /// position to this transport's handle. Changes made through this
+    /// transport's handle are replaced by the outer graph's state.
+    #[inline(always)]
+    pub(crate) fn follow(&mut self, outer: &Transport) {
+        self.sample_rate = outer.sample_rate;
+        self.playing = outer.playing;
+        self.tempo = outer.tempo;
+        self.time_signature = outer.time_signature;
+        self.loop_range = outer.loop_range;
+        self.beat_position = outer.beat_position;
+        self.sample_position = outer.sample_position;
+        let shared = &self.handle.shared;
+        shared.beat_position.store(self.beat_position.to_bits(), Ordering::Relaxed);
+        shared.sample_position.store(self.sample_position, Ordering::Relaxed);
+    }
+}
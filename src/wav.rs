@@ -0,0 +1,142 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum WavError {
+    Io(io::Error),
+    NotRiffWave,
+    MissingChunk(&'static str),
+    /// Only integer PCM of 8 to 32 bits and 32 or 64 bit floats are read.
+    UnsupportedFormat,
+}
+
+impl From<io::Error> for WavError {
+    fn from(error: io::Error) -> Self {
+        WavError::Io(error)
+    }
+}
+
+/// Decoded audio, one `Vec` of samples in `-1.0..=1.0` per channel.
+pub struct Wav {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Reads a WAV file. This is meant for loading impulse responses and
+/// other material ahead of time, not from the audio thread.
+pub fn read_wav(path: impl AsRef<Path>) -> Result<Wav, WavError> {
+    parse_wav(&fs::read(path)?)
+}
+
+/// Decodes the bytes of a WAV file.
+pub fn parse_wav(bytes: &[u8]) -> Result<Wav, WavError> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(WavError::NotRiffWave);
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut at = 12;
+    while at + 8 <= bytes.len() {
+        let id = &bytes[at..at + 4];
+        let len = u32_at(bytes, at + 4) as usize;
+        let body = &bytes[at + 8..(at + 8 + len).min(bytes.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => format = Some(body),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        // Chunks are padded to an even length.
+        at += 8 + len + (len & 1);
+    }
+    let format = format.ok_or(WavError::MissingChunk("fmt "))?;
+    let data = data.ok_or(WavError::MissingChunk("data"))?;
+
+    let mut tag = u16_at(format, 0);
+    if tag == FORMAT_EXTENSIBLE && format.len() >= 26 {
+        tag = u16_at(format, 24);
+    }
+    let channel_count = u16_at(format, 2) as usize;
+    let sample_rate = u32_at(format, 4);
+    let block_align = u16_at(format, 12) as usize;
+    let bits = u16_at(format, 14) as usize;
+    let width = bits.div_ceil(8);
+    if channel_count == 0 || width == 0 {
+        return Err(WavError::UnsupportedFormat);
+    }
+    // Extensible files may store samples in wider containers, such as
+    // 24 bits in four bytes, with the valid bits at the top.
+    let container = block_align / channel_count;
+    if container < width {
+        return Err(WavError::UnsupportedFormat);
+    }
+
+    let decode: fn(&[u8]) -> f32 = match (tag, width) {
+        (FORMAT_PCM, 1) => |b| (b[0] as f32 - 128.0) / 128.0,
+        (FORMAT_PCM, 2) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32_768.0,
+        (FORMAT_PCM, 3) => |b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0,
+        (FORMAT_PCM, 4) => |b| (i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64 / 2_147_483_648.0) as f32,
+        (FORMAT_FLOAT, 4) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        (FORMAT_FLOAT, 8) => |b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32,
+        _ => return Err(WavError::UnsupportedFormat),
+    };
+
+    let mut channels = vec![Vec::with_capacity(data.len() / block_align); channel_count];
+    for frame in data.chunks_exact(block_align) {
+        for (channel, sample) in channels.iter_mut().zip(frame.chunks_exact(container)) {
+            channel.push(decode(&sample[container - width..]));
+        }
+    }
+
+    Ok(Wav { sample_rate, channels })
+}
+
+/// Writes `wav` to a file as 32 bit float samples. Like [`read_wav`],
+/// this is meant for use away from the audio thread, such as saving
+/// stems rendered with [`crate::write::bounce_buses`].
+pub fn write_wav(path: impl AsRef<Path>, wav: &Wav) -> Result<(), WavError> {
+    Ok(fs::write(path, encode_wav(wav))?)
+}
+
+/// Encodes `wav` as the bytes of a WAV file of 32 bit float samples.
+/// Channels shorter than the first are padded with silence.
+pub fn encode_wav(wav: &Wav) -> Vec<u8> {
+    let channel_count = wav.channels.len();
+    let frames = wav.channels.first().map_or(0, Vec::len);
+    let data_len = (frames * channel_count * 4) as u32;
+    let block_align = (channel_count * 4) as u16;
+
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&FORMAT_FLOAT.to_le_bytes());
+    bytes.extend_from_slice(&(channel_count as u16).to_le_bytes());
+    bytes.extend_from_slice(&wav.sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(wav.sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&32u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for frame in 0..frames {
+        for channel in &wav.channels {
+            let sample = channel.get(frame).copied().unwrap_or(0.0);
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+    }
+    bytes
+}
@@ -0,0 +1,394 @@
+use core::f64::consts::PI;
+
+use crate::audio_context::AudioContext;
+use crate::buffer::Frame;
+use crate::node::Node;
+use crate::param::{Choice, ParamError, Params, Value};
+use crate::sample::Sample;
+
+/// Filter taps per polyphase branch. Higher factors get longer filters,
+/// keeping the same transition band relative to the input rate.
+const TAPS_PER_PHASE: usize = 16;
+const MAX_FACTOR: usize = 8;
+
+/// The transfer curve a [`Waveshaper`] applies.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Shape {
+    /// Smooth symmetric saturation.
+    #[default]
+    Tanh,
+    /// Asymmetric saturation like a diode clipper, where the negative half
+    /// clips earlier, adding even harmonics and some DC.
+    Diode,
+    /// Reflects anything beyond `-1.0..=1.0` back into range.
+    Foldback,
+    /// A user transfer curve, with the first value the output at an input
+    /// of `-1.0` and the last at `1.0`, interpolated between. Inputs past
+    /// the ends hold the end values.
+    Table(Vec<f32>),
+}
+
+impl Shape {
+    /// Saved by name, or as the list of values for a table.
+    fn to_value(&self) -> Value {
+        match self {
+            Shape::Tanh => "tanh".into(),
+            Shape::Diode => "diode".into(),
+            Shape::Foldback => "foldback".into(),
+            Shape::Table(table) => Value::from_floats(table.iter().copied()),
+        }
+    }
+    fn from_value(value: &Value) -> Result<Self, ParamError> {
+        match value {
+            Value::List(_) => Ok(Shape::Table(value.floats()?)),
+            _ => match value.text()? {
+                "tanh" => Ok(Shape::Tanh),
+                "diode" => Ok(Shape::Diode),
+                "foldback" => Ok(Shape::Foldback),
+                _ => Err(ParamError::Invalid),
+            },
+        }
+    }
+    #[inline(always)]
+    pub fn apply<S: Sample>(&self, x: S) -> S {
+        match self {
+            Shape::Tanh => x.tanh(),
+            Shape::Diode => match x >= S::ZERO {
+                true => S::ONE - (-x).exp(),
+                false => S::from_f32(-0.5) * (S::ONE - (x * S::from_f32(2.0)).exp()),
+            },
+            Shape::Foldback => {
+                let x = x - S::ONE;
+                let wrapped = x - (x * S::from_f32(0.25)).floor() * S::from_f32(4.0);
+                (wrapped - S::from_f32(2.0)).abs() - S::ONE
+            }
+            Shape::Table(table) => match table.len() {
+                0 => x,
+                1 => S::from_f32(table[0]),
+                len => {
+                    let position = ((x.to_f32() + 1.0) * 0.5 * (len - 1) as f32).clamp(0.0, (len - 1) as f32);
+                    let index = (position as usize).min(len - 2);
+                    let fraction = position - index as f32;
+                    S::from_f32(table[index] + (table[index + 1] - table[index]) * fraction)
+                }
+            },
+        }
+    }
+}
+
+/// How many times faster than the graph a [`Waveshaper`] runs its curve,
+/// pushing the harmonics it adds above the audible band before they can
+/// fold back down as aliasing.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Oversampling {
+    #[default]
+    None,
+    X2,
+    X4,
+    X8,
+}
+
+impl Choice for Oversampling {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("none", Oversampling::None),
+        ("x2", Oversampling::X2),
+        ("x4", Oversampling::X4),
+        ("x8", Oversampling::X8),
+    ];
+}
+
+impl Oversampling {
+    pub fn factor(self) -> usize {
+        match self {
+            Oversampling::None => 1,
+            Oversampling::X2 => 2,
+            Oversampling::X4 => 4,
+            Oversampling::X8 => 8,
+        }
+    }
+}
+
+/// A windowed sinc lowpass at the input rate's Nyquist, for a rate
+/// `factor` times higher, with unity gain at DC.
+fn lowpass(factor: usize) -> Vec<f64> {
+    let len = factor * TAPS_PER_PHASE;
+    let cutoff = 0.45 / factor as f64;
+    let centre = (len - 1) as f64 * 0.5;
+    let mut taps: Vec<f64> = (0..len)
+        .map(|i| {
+            let t = i as f64 - centre;
+            let sinc = match t == 0.0 {
+                true => 2.0 * cutoff,
+                false => (2.0 * PI * cutoff * t).sin() / (PI * t),
+            };
+            let window = 0.42 - 0.5 * (2.0 * PI * i as f64 / (len - 1) as f64).cos()
+                + 0.08 * (4.0 * PI * i as f64 / (len - 1) as f64).cos();
+            sinc * window
+        })
+        .collect();
+    let sum: f64 = taps.iter().sum();
+    taps.iter_mut().for_each(|tap| *tap /= sum);
+    taps
+}
+
+/// Polyphase interpolation and decimation for one channel. Both keep
+/// their history twice over, so the newest samples are always one
+/// contiguous slice, newest first.
+struct Resampler<S: Sample> {
+    factor: usize,
+    /// The interpolation filter split into `factor` branches, each
+    /// computing one of the new samples from the input history alone
+    /// instead of filtering the zeros a naive upsampler would stuff in.
+    up: Vec<S>,
+    up_history: Vec<S>,
+    up_position: usize,
+    down: Vec<S>,
+    down_history: Vec<S>,
+    down_position: usize,
+}
+
+impl<S: Sample> Resampler<S> {
+    fn new(factor: usize) -> Self {
+        let taps = lowpass(factor);
+        let mut up = vec![S::ZERO; taps.len()];
+        for (i, &tap) in taps.iter().enumerate() {
+            up[(i % factor) * TAPS_PER_PHASE + i / factor] = S::from_f64(tap * factor as f64);
+        }
+        Self {
+            factor,
+            up,
+            up_history: vec![S::ZERO; TAPS_PER_PHASE * 2],
+            up_position: 0,
+            down: taps.iter().map(|&tap| S::from_f64(tap)).collect(),
+            down_history: vec![S::ZERO; taps.len() * 2],
+            down_position: 0,
+        }
+    }
+
+    /// Delay through both filters, in input samples.
+    fn latency(&self) -> usize {
+        (self.down.len() - 1) / self.factor
+    }
+
+    #[inline(always)]
+    fn upsample(&mut self, input: S, output: &mut [S]) {
+        self.up_position = (self.up_position + TAPS_PER_PHASE - 1) % TAPS_PER_PHASE;
+        self.up_history[self.up_position] = input;
+        self.up_history[self.up_position + TAPS_PER_PHASE] = input;
+        let history = &self.up_history[self.up_position..self.up_position + TAPS_PER_PHASE];
+        for (out, branch) in output.iter_mut().zip(self.up.chunks_exact(TAPS_PER_PHASE)) {
+            let mut sum = S::ZERO;
+            for (&tap, &x) in branch.iter().zip(history) {
+                sum += tap * x;
+            }
+            *out = sum;
+        }
+    }
+
+    /// Filters `input` and keeps only the last sample, the one the
+    /// decimated output needs.
+    #[inline(always)]
+    fn downsample(&mut self, input: &[S]) -> S {
+        let len = self.down.len();
+        for &x in input {
+            self.down_position = (self.down_position + len - 1) % len;
+            self.down_history[self.down_position] = x;
+            self.down_history[self.down_position + len] = x;
+        }
+        let history = &self.down_history[self.down_position..self.down_position + len];
+        let mut sum = S::ZERO;
+        for (&tap, &x) in self.down.iter().zip(history) {
+            sum += tap * x;
+        }
+        sum
+    }
+}
+
+/// Drives the input into a transfer curve. With oversampling the curve
+/// runs at a multiple of the sample rate between polyphase interpolation
+/// and decimation filters, which costs `latency()` samples of delay.
+///
+/// Filters are allocated on construction.
+pub struct Waveshaper<const N: usize, const C: usize, S: Sample = f32> {
+    shape: Shape,
+    oversampling: Oversampling,
+    resamplers: Vec<Resampler<S>>,
+    drive: f32,
+    output: f32,
+    mix: f32,
+}
+
+impl<const N: usize, const C: usize, S: Sample> Waveshaper<N, C, S> {
+    pub fn new(shape: Shape, oversampling: Oversampling) -> Self {
+        let resamplers = match oversampling {
+            Oversampling::None => Vec::new(),
+            _ => (0..C).map(|_| Resampler::new(oversampling.factor())).collect(),
+        };
+        Self {
+            shape,
+            oversampling,
+            resamplers,
+            drive: 1.0,
+            output: 1.0,
+            mix: 1.0,
+        }
+    }
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+    }
+    pub fn oversampling(&self) -> Oversampling {
+        self.oversampling
+    }
+    /// The gain into the curve.
+    pub fn set_drive(&mut self, drive: f32) {
+        self.drive = drive;
+    }
+    /// The gain after the curve.
+    pub fn set_output(&mut self, output: f32) {
+        self.output = output;
+    }
+    /// From `0.0`, only dry, to `1.0`, only shaped. The dry signal is not
+    /// delayed to match the oversampling filters, so mixing colours the
+    /// sound slightly.
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0.0, 1.0);
+    }
+    /// The delay the oversampling filters add, in samples.
+    pub fn latency(&self) -> usize {
+        self.resamplers.first().map_or(0, Resampler::latency)
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Waveshaper<N, C, S> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let input = &inputs[0];
+        let factor = self.oversampling.factor();
+        let drive = S::from_f32(self.drive);
+        let wet = S::from_f32(self.output * self.mix);
+        let dry = S::from_f32(1.0 - self.mix);
+        let mut oversampled = [S::ZERO; MAX_FACTOR];
+
+        for c in 0..C {
+            for n in 0..ctx.frames() {
+                let x = input[c][n];
+                let shaped = match self.resamplers.get_mut(c) {
+                    Some(resampler) => {
+                        let oversampled = &mut oversampled[..factor];
+                        resampler.upsample(x * drive, oversampled);
+                        for sample in oversampled.iter_mut() {
+                            *sample = self.shape.apply(*sample);
+                        }
+                        resampler.downsample(oversampled)
+                    }
+                    None => self.shape.apply(x * drive),
+                };
+                output[c][n] = shaped * wet + x * dry;
+            }
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("waveshaper")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("shape", self.shape.to_value())
+            .with("oversampling", Value::from_choice(self.oversampling))
+            .with("drive", self.drive)
+            .with("output", self.output)
+            .with("mix", self.mix)
+    }
+
+    /// Changing the oversampling rebuilds the filters.
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "shape" => self.set_shape(Shape::from_value(value)?),
+            "oversampling" => {
+                let oversampling = value.choice()?;
+                if oversampling != self.oversampling {
+                    let (drive, output, mix) = (self.drive, self.output, self.mix);
+                    *self = Self::new(core::mem::take(&mut self.shape), oversampling);
+                    (self.drive, self.output, self.mix) = (drive, output, mix);
+                }
+            }
+            "drive" => self.set_drive(value.float()?),
+            "output" => self.set_output(value.float()?),
+            "mix" => self.set_mix(value.float()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
+
+/// Reduces bit depth and sample rate for lo-fi grit. The aliasing and
+/// quantisation noise are the point here, so nothing is oversampled.
+pub struct Bitcrusher<const N: usize, const C: usize, S: Sample = f32> {
+    bits: f32,
+    decimation: f32,
+    held: [S; C],
+    phase: f32,
+}
+
+impl<const N: usize, const C: usize, S: Sample> Bitcrusher<N, C, S> {
+    pub fn new(bits: f32, decimation: f32) -> Self {
+        let mut crusher = Self {
+            bits: 0.0,
+            decimation: 1.0,
+            held: [S::ZERO; C],
+            phase: 1.0,
+        };
+        crusher.set_bits(bits);
+        crusher.set_decimation(decimation);
+        crusher
+    }
+    /// From one to 24 bits, fractional values stepping smoothly between.
+    pub fn set_bits(&mut self, bits: f32) {
+        self.bits = bits.clamp(1.0, 24.0);
+    }
+    /// How many samples each held sample lasts, from `1.0`, no decimation.
+    /// Fractional values hold for an uneven number of samples.
+    pub fn set_decimation(&mut self, decimation: f32) {
+        self.decimation = decimation.max(1.0);
+    }
+}
+
+impl<const N: usize, const C: usize, S: Sample> Node<N, C, S> for Bitcrusher<N, C, S> {
+    fn process(&mut self, ctx: &AudioContext, inputs: &[Frame<N, C, S>], output: &mut Frame<N, C, S>) {
+        let input = &inputs[0];
+        let steps = S::from_f32(2f32.powf(self.bits - 1.0));
+        let step = 1.0 / self.decimation;
+
+        for n in 0..ctx.frames() {
+            if self.phase >= 1.0 {
+                self.phase -= 1.0;
+                for (held, channel) in self.held.iter_mut().zip(input) {
+                    *held = (channel[n] * steps + S::from_f32(0.5)).floor() / steps;
+                }
+            }
+            self.phase += step;
+            for (out, &held) in output.iter_mut().zip(&self.held) {
+                out[n] = held;
+            }
+        }
+    }
+
+    fn type_name(&self) -> Option<&'static str> {
+        Some("bitcrusher")
+    }
+
+    fn params(&self) -> Params {
+        Params::new()
+            .with("bits", self.bits)
+            .with("decimation", self.decimation)
+    }
+
+    fn set_param(&mut self, name: &str, value: &Value) -> Result<(), ParamError> {
+        match name {
+            "bits" => self.set_bits(value.float()?),
+            "decimation" => self.set_decimation(value.float()?),
+            _ => return Err(ParamError::Unknown),
+        }
+        Ok(())
+    }
+}
@@ -1,27 +1,119 @@
 use cpal::{FromSample, SizedSample};
 
 use crate::audio_graph::DynamicAudioGraph;
+use crate::sample::Sample;
+use crate::wav::Wav;
 
 // / The function that takes an input from the audio pipeline, 
 // / and delivers it to the CPAL slice. The CPAL slice is a 
 // / frame of a certain buffer size. If you request a buffer size of 256,
 // / with 2 channels, the output will have a length of 512. This function
 // / also takes ownership of the audio pipeline.
+// / Graph samples are converted to the device format here, and nowhere else.
+// / Hosts asking for more frames than the graph's buffer size are served
+// / in several blocks, and fewer frames only compute what is needed.
 #[inline(always)]
-pub fn write_data<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize, T>(
+pub fn write_data<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize, T, S>(
     output: &mut [T],
-    audio_graph: &mut DynamicAudioGraph<BUFFER_SIZE, CHANNEL_COUNT>
+    audio_graph: &mut DynamicAudioGraph<BUFFER_SIZE, CHANNEL_COUNT, S>
 )
 where
     T: SizedSample + FromSample<f64>,
+    S: Sample,
 {    
-    
-    let next_pipeline_buffer = audio_graph.next_block();
+    for block in output.chunks_mut(BUFFER_SIZE * CHANNEL_COUNT) {
+        let frames = block.len() / CHANNEL_COUNT;
+        let next_pipeline_buffer = audio_graph.next_block(frames);
 
-    for (frame_index, frame) in output.chunks_mut(CHANNEL_COUNT).enumerate() {
-        for (channel, sample) in frame.iter_mut().enumerate() {
-            let pipeline_next_frame = &next_pipeline_buffer[channel];
-            *sample = T::from_sample(pipeline_next_frame[frame_index] as f64);
+        for (frame_index, frame) in block.chunks_mut(CHANNEL_COUNT).enumerate() {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let pipeline_next_frame = &next_pipeline_buffer[channel];
+                *sample = T::from_sample(pipeline_next_frame[frame_index].to_f64());
+            }
         }
     }
 }
+
+/// Sends some of the channels of an output bus to a device: `channels`
+/// channels of `bus`, starting with the device's `first_channel`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BusRoute {
+    pub bus: usize,
+    pub first_channel: usize,
+    pub channels: usize,
+}
+
+impl BusRoute {
+    pub fn new(bus: usize, first_channel: usize, channels: usize) -> Self {
+        Self { bus, first_channel, channels }
+    }
+}
+
+/// Like [`write_data`], for a device with `device_channels` interleaved
+/// channels fed from the graph's [buses](DynamicAudioGraph::add_bus)
+/// rather than its sink, such as a main mix on the first two channels
+/// and a monitor mix on the next two. Routes reaching the same device
+/// channel are summed, and channels no route reaches are silent.
+#[inline(always)]
+pub fn write_buses<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize, T, S>(
+    output: &mut [T],
+    device_channels: usize,
+    audio_graph: &mut DynamicAudioGraph<BUFFER_SIZE, CHANNEL_COUNT, S>,
+    routes: &[BusRoute],
+)
+where
+    T: SizedSample + FromSample<f64>,
+    S: Sample,
+{
+    if device_channels == 0 {
+        return;
+    }
+    for block in output.chunks_mut(BUFFER_SIZE * device_channels) {
+        let frames = block.len() / device_channels;
+        audio_graph.next_block(frames);
+
+        for (frame_index, frame) in block.chunks_mut(device_channels).enumerate() {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let mut sum = 0.0;
+                for route in routes {
+                    let Some(bus_channel) = channel.checked_sub(route.first_channel) else { continue };
+                    if bus_channel < route.channels.min(CHANNEL_COUNT) {
+                        sum += audio_graph.bus(route.bus)[bus_channel][frame_index].to_f64();
+                    }
+                }
+                *sample = T::from_sample(sum);
+            }
+        }
+    }
+}
+
+/// Renders `frames` frames of the graph ahead of time and returns what
+/// each of `buses` carried, with as many channels as its node writes,
+/// for saving stems with [`write_wav`](crate::wav::write_wav).
+pub fn bounce_buses<const BUFFER_SIZE: usize, const CHANNEL_COUNT: usize, S: Sample>(
+    audio_graph: &mut DynamicAudioGraph<BUFFER_SIZE, CHANNEL_COUNT, S>,
+    frames: usize,
+    buses: &[usize],
+) -> Vec<Wav> {
+    let sample_rate = *audio_graph.context().get_sample_rate() as u32;
+    let mut stems: Vec<Wav> = buses
+        .iter()
+        .map(|&bus| Wav {
+            sample_rate,
+            channels: vec![Vec::with_capacity(frames); audio_graph.output_channels(audio_graph.bus_node(bus))],
+        })
+        .collect();
+    let mut remaining = frames;
+    while remaining > 0 {
+        let block = remaining.min(BUFFER_SIZE);
+        audio_graph.next_block(block);
+        for (stem, &bus) in stems.iter_mut().zip(buses) {
+            let output = audio_graph.bus(bus);
+            for (channel, buffer) in stem.channels.iter_mut().zip(output) {
+                channel.extend(buffer[..block].iter().map(|sample| sample.to_f32()));
+            }
+        }
+        remaining -= block;
+    }
+    stems
+}